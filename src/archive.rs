@@ -0,0 +1,526 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Mutex;
+
+use crate::log::InstallLog;
+use crate::{
+    check_wrappers, crackem, err, info, lock, make_sure_if_empty, preinstall, warn, Cracker,
+    CrackerDatabase, CrackerFileSystem, Executor, InstallReport, InstallationType, LinkMode,
+    OptArchive, Paths, Prompt,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// compressed or not, tar figures the compression out by itself.
+    Tar,
+    Zip,
+}
+
+const EXTENSIONS: [(&str, Format); 8] = [
+    (".tar.gz", Format::Tar),
+    (".tgz", Format::Tar),
+    (".tar.xz", Format::Tar),
+    (".txz", Format::Tar),
+    (".tar.bz2", Format::Tar),
+    (".tbz2", Format::Tar),
+    (".tar", Format::Tar),
+    (".zip", Format::Zip),
+];
+
+/// Whether `source` has to be downloaded rather than copied.
+fn is_url(source: &str) -> bool {
+    source.contains("://")
+}
+
+/// Last component of `source`, the query of a URL left out.
+fn file_name(source: &str) -> &str {
+    let source = if is_url(source) {
+        source.split(['?', '#']).next().unwrap_or(source)
+    } else {
+        source
+    };
+    source
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(source)
+}
+
+/// The name of the archive without its extension and its format.
+fn split_extension(file_name: &str) -> err::Result<(&str, Format)> {
+    EXTENSIONS
+        .iter()
+        .find(|(extension, _)| file_name.to_lowercase().ends_with(extension))
+        .map(|(extension, format)| (&file_name[..file_name.len() - extension.len()], *format))
+        .ok_or_else(|| {
+            let known = EXTENSIONS.iter().map(|(e, _)| (*e).to_owned()).collect();
+            err::ErrorKind::ArchiveUnknownFormat(file_name.to_owned(), known).into()
+        })
+}
+
+pub(crate) fn archive_paths(i: &OptArchive) -> err::Result<Paths> {
+    let (stem, _) = split_extension(file_name(&i.source))?;
+    Ok(Paths::new(
+        i.prefix.clone(),
+        i.bin_dir.clone(),
+        InstallationType::Archive,
+        i.name.as_deref().unwrap_or(stem),
+    ))
+}
+
+/// Puts the archive `source` points at into `destination`.
+fn fetch<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    source: &str,
+    destination: &Path,
+) -> err::Result<()> {
+    if !is_url(source) {
+        info(&format!("Copying archive: {}", source));
+        return Ok(fs.copy_file(source, destination)?);
+    }
+
+    info(&format!("Downloading archive: {}", source));
+    let mut c = Command::new("curl");
+    c.args(["--fail", "--silent", "--show-error", "--location"])
+        .arg("--output")
+        .arg(destination)
+        .arg(source);
+    let output = run_tool(executor, c)?;
+    if !output.status.success() {
+        return Err(err::ErrorKind::ArchiveDownloadFailure(source.to_owned(), output).into());
+    }
+
+    Ok(())
+}
+
+/// Runs curl, sha256sum, tar or unzip, naming the one missing from the path.
+fn run_tool(executor: &dyn Executor, c: Command) -> err::Result<Output> {
+    let tool = c.get_program().to_string_lossy().into_owned();
+    executor.run(c).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => err::ErrorKind::ArchiveToolNotFound(tool).into(),
+        _ => e.into(),
+    })
+}
+
+fn sha256(executor: &dyn Executor, path: &Path) -> err::Result<String> {
+    let mut c = Command::new("sha256sum");
+    c.arg(path);
+    let output = run_tool(executor, c)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.split_whitespace().next() {
+        Some(sum) if output.status.success() => Ok(sum.to_lowercase()),
+        _ => Err(err::ErrorKind::ArchiveChecksumFailure(path.to_path_buf(), output).into()),
+    }
+}
+
+fn unpack(
+    executor: &dyn Executor,
+    source: &str,
+    archive: &Path,
+    format: Format,
+    destination: &Path,
+) -> err::Result<()> {
+    info(&format!("Unpacking archive: {}", source));
+    let (program, flag, into) = match format {
+        Format::Tar => ("tar", "-xf", "-C"),
+        Format::Zip => ("unzip", "-q", "-d"),
+    };
+    let mut c = Command::new(program);
+    c.arg(flag).arg(archive).arg(into).arg(destination);
+    let output = run_tool(executor, c)?;
+    if !output.status.success() {
+        return Err(err::ErrorKind::ArchiveUnpackFailure(source.to_owned(), output).into());
+    }
+
+    Ok(())
+}
+
+/// Moves what is left of `unpacked` once `components` leading path components are dropped into
+/// `install_folder`, like `tar --strip-components` does.
+fn strip_components<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    unpacked: &Path,
+    components: usize,
+    install_folder: &Path,
+) -> err::Result<()> {
+    if components == 0 {
+        return Ok(fs.rename(unpacked, install_folder)?);
+    }
+
+    let kept: Vec<PathBuf> = fs
+        .walk(unpacked, Some(components + 1))?
+        .into_iter()
+        .filter(|p| {
+            p.strip_prefix(unpacked)
+                .is_ok_and(|p| p.components().count() == components + 1)
+        })
+        .collect();
+    if kept.is_empty() {
+        return Err(err::ErrorKind::ArchiveEmptyAfterStrip(components).into());
+    }
+
+    fs.create_dir_all(install_folder)?;
+    for p in kept {
+        let name = p.file_name().ok_or("archive entry without a name")?;
+        fs.rename(&p, install_folder.join(name))?;
+    }
+    fs.remove_dir_all(unpacked)?;
+
+    Ok(())
+}
+
+/// Fetches, checks and unpacks the archive into the install folder, `downloaded` and `unpacked`
+/// are scratch space of the caller.
+fn extract<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    i: &OptArchive,
+    downloaded: &Path,
+    unpacked: &Path,
+    install_folder: &Path,
+) -> err::Result<()> {
+    let (_, format) = split_extension(file_name(&i.source))?;
+    fetch(fs, executor, &i.source, downloaded)?;
+    if let Some(expected) = &i.sha256 {
+        let actual = sha256(executor, downloaded)?;
+        if actual != expected.to_lowercase() {
+            return Err(err::ErrorKind::ArchiveChecksumMismatch(
+                i.source.clone(),
+                expected.clone(),
+                actual,
+            )
+            .into());
+        }
+    }
+
+    fs.create_dir_all(unpacked)?;
+    unpack(executor, &i.source, downloaded, format, unpacked)?;
+    strip_components(fs, unpacked, i.strip_components, install_folder)
+}
+
+pub(crate) fn install_archive<Fs, P>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    prompt: &P,
+    i: &OptArchive,
+    paths: &Paths,
+    db: &Mutex<CrackerDatabase>,
+) -> err::Result<InstallReport>
+where
+    Fs: CrackerFileSystem,
+    P: Prompt + ?Sized,
+{
+    let _log = InstallLog::start(fs, i.log.then(|| paths.install_log()));
+    let pkg_name = paths.pkg_name.clone();
+    let mut report = InstallReport::default();
+    if !make_sure_if_empty(fs, prompt, &pkg_name, paths, &mut lock(db))? {
+        warn("Unable to install package.");
+        return Ok(report);
+    }
+
+    let install_folder = paths.install_folder();
+    let downloads = paths.downloads_dir();
+    // keyed by package, sources of different packages may share a file name.
+    let downloaded = downloads.join(format!("{}.{}", pkg_name, file_name(&i.source)));
+    let unpacked = downloads.join(format!("{}.unpacked", pkg_name));
+    // leftovers of an install that crashed midway.
+    if fs.is_dir(&unpacked) {
+        fs.remove_dir_all(&unpacked)?;
+    }
+    fs.create_dir_all(&downloads)?;
+    let extracted = extract(fs, executor, i, &downloaded, &unpacked, &install_folder);
+    for scratch in [&downloaded, &unpacked] {
+        let removed = if fs.is_dir(scratch) {
+            fs.remove_dir_all(scratch)
+        } else if fs.is_file(scratch) {
+            fs.remove_file(scratch)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = removed {
+            warn(&format!(
+                "Unable to remove {}: {}, continued.",
+                scratch.display(),
+                e
+            ));
+        }
+    }
+    extracted?;
+
+    let roots: Vec<PathBuf> = i
+        .search_paths
+        .iter()
+        .map(|p| install_folder.join(p))
+        .collect();
+    check_wrappers(fs, &install_folder, &roots, &i.discovery)?;
+    let mut db = lock(db);
+    for path in roots {
+        report.extend(crackem(
+            fs,
+            prompt,
+            paths,
+            &mut db,
+            err::path_str(&path)?.to_owned(),
+            &i.discovery,
+            i.data(&pkg_name),
+            LinkMode::Script,
+        )?);
+    }
+
+    report.entry = db.entry(&install_folder).cloned();
+    Ok(report)
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem + Sync,
+    E: Executor,
+    P: Prompt,
+{
+    /// Installs a prebuilt archive and wraps the executables found in its search paths.
+    pub fn archive(&self, i: OptArchive) -> err::Result<InstallReport> {
+        // the source ends up in the index, an import has to find it from anywhere.
+        let source = if is_url(&i.source) {
+            i.source
+        } else {
+            let path = self
+                .fs
+                .canonicalize(Path::new(&i.source))
+                .map_err(|e| err::ErrorKind::PathNotFound(i.source.clone(), e.to_string()))?;
+            err::path_str(&path)?.to_owned()
+        };
+        let i = OptArchive {
+            prefix: self.config.prefix(i.prefix),
+            bin_dir: self.config.bin_dir(i.bin_dir),
+            source,
+            ..i
+        };
+        let paths = archive_paths(&i)?.with_cracker(&self.cracker);
+        let db = Mutex::new(preinstall(&self.fs, &paths)?);
+
+        let prompt = self.policy_prompt(&self.prompt);
+        let report = install_archive(&self.fs, &self.executor, &prompt, &i, &paths, &db)?;
+        self.share_storage(&paths)?;
+        let db = db.into_inner().unwrap_or_else(|e| e.into_inner());
+        db.save(&self.fs, paths.db_path())?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+    use crate::executor::{output, ScriptedExecutor};
+    use crate::{AssumeYes, CrackerDatabaseData};
+    use filesystem::{FileSystem, TempDir, TempFileSystem};
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn archive_names() {
+        assert_eq!(
+            file_name("https://example.com/rg-14.tar.gz?raw=1"),
+            "rg-14.tar.gz"
+        );
+        assert_eq!(file_name("/downloads/shellcheck.zip"), "shellcheck.zip");
+        assert_eq!(
+            split_extension("rg-14.TAR.GZ").unwrap(),
+            ("rg-14", Format::Tar)
+        );
+        assert_eq!(
+            split_extension("shellcheck.zip").unwrap(),
+            ("shellcheck", Format::Zip)
+        );
+        assert!(split_extension("tool.deb")
+            .unwrap_err()
+            .to_string()
+            .contains("unknown archive format of tool.deb"));
+    }
+
+    #[test]
+    fn archive_install_end_to_end() {
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = fs.temp_dir("cracker").unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let downloads = prefix.join(".cracker_storage/.downloads");
+        let downloaded = downloads.join("tool.tool-1.0.tar.gz");
+        let unpacked = downloads.join("tool.unpacked");
+        let if_path = prefix.join(".cracker_storage/archive_tool");
+        let opt = |sha256: &str| OptArchive {
+            prefix: Some(prefix.clone()),
+            bin_dir: None,
+            source: String::from("https://example.com/tool-1.0.tar.gz"),
+            name: Some(String::from("tool")),
+            sha256: Some(sha256.to_owned()),
+            strip_components: 1,
+            discovery: Default::default(),
+            search_paths: vec![String::from("bin")],
+            log: false,
+        };
+        let curl = format!(
+            "curl --fail --silent --show-error --location --output {} https://example.com/tool-1.0.tar.gz",
+            downloaded.display()
+        );
+        let download = {
+            let downloaded = downloaded.clone();
+            move |_: &Command| {
+                std::fs::write(&downloaded, "archive")?;
+                Ok(output(0, "", ""))
+            }
+        };
+        let sha256sum = format!("sha256sum {}", downloaded.display());
+
+        let cracker = Cracker::new(
+            fs,
+            ScriptedExecutor::new()
+                .expect_with(&curl, download.clone())
+                .expect(&sha256sum, &format!("abc123  {}\n", downloaded.display())),
+            AssumeYes,
+        );
+        let e = cracker.archive(opt("ABC124")).err().unwrap();
+        assert_eq!(e.kind().name(), "ArchiveChecksumMismatch");
+        assert!(!if_path.exists());
+        assert!(!downloaded.exists());
+
+        let tar = format!("tar -xf {} -C {}", downloaded.display(), unpacked.display());
+        let cracker = Cracker::new(
+            cracker.fs,
+            ScriptedExecutor::new()
+                .expect_with(&curl, download)
+                .expect(&sha256sum, &format!("abc123  {}\n", downloaded.display()))
+                .expect_with(&tar, {
+                    let unpacked = unpacked.clone();
+                    move |_| {
+                        let bin = unpacked.join("tool-1.0/bin");
+                        std::fs::create_dir_all(&bin)?;
+                        std::fs::write(bin.join("tool"), "#!/bin/sh")?;
+                        std::fs::set_permissions(
+                            bin.join("tool"),
+                            std::fs::Permissions::from_mode(0o755),
+                        )?;
+                        std::fs::write(unpacked.join("tool-1.0/README"), "")?;
+                        Ok(output(0, "", ""))
+                    }
+                }),
+            AssumeYes,
+        );
+        let report = cracker.archive(opt("ABC123")).unwrap();
+        cracker.executor.verify();
+
+        assert_eq!(report.wrappers_created, vec![String::from("tool")]);
+        assert!(if_path.join("README").is_file());
+        assert!(prefix.join("bin/tool").is_file());
+        assert!(!downloaded.exists());
+        assert!(!unpacked.exists());
+        assert_eq!(
+            report.entry.unwrap().data,
+            CrackerDatabaseData::Archive {
+                pkg_name: String::from("tool"),
+                source: String::from("https://example.com/tool-1.0.tar.gz"),
+                sha256: Some(String::from("ABC123")),
+                strip_components: 1,
+                search_paths: vec![String::from("bin")],
+                search_depth: 1,
+                exclude: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn same_file_names_download_apart() {
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = fs.temp_dir("cracker").unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let downloads = prefix.join(".cracker_storage/.downloads");
+        let mut executor = ScriptedExecutor::new();
+        for name in ["one", "two"] {
+            let source = format!("https://{}.example.com/release.tar.gz", name);
+            let downloaded = downloads.join(format!("{}.release.tar.gz", name));
+            let unpacked = downloads.join(format!("{}.unpacked", name));
+            let curl = format!(
+                "curl --fail --silent --show-error --location --output {} {}",
+                downloaded.display(),
+                source
+            );
+            let tar = format!("tar -xf {} -C {}", downloaded.display(), unpacked.display());
+            executor = executor
+                .expect_with(&curl, {
+                    let downloaded = downloaded.clone();
+                    move |_| {
+                        std::fs::write(&downloaded, "archive")?;
+                        Ok(output(0, "", ""))
+                    }
+                })
+                .expect_with(&tar, move |_| {
+                    std::fs::write(unpacked.join(name), "#!/bin/sh")?;
+                    std::fs::set_permissions(
+                        unpacked.join(name),
+                        std::fs::Permissions::from_mode(0o755),
+                    )?;
+                    Ok(output(0, "", ""))
+                });
+        }
+
+        let cracker = Cracker::new(fs, executor, AssumeYes);
+        for name in ["one", "two"] {
+            let mut i = OptArchive::new(format!("https://{}.example.com/release.tar.gz", name));
+            i.prefix = Some(prefix.clone());
+            i.name = Some(name.to_owned());
+            cracker.archive(i).unwrap();
+        }
+        cracker.executor.verify();
+        assert!(prefix.join("bin/one").is_file());
+        assert!(prefix.join("bin/two").is_file());
+    }
+
+    #[test]
+    fn missing_tools_are_named() {
+        let fs = filesystem::FakeFileSystem::new();
+        let missing = |_: &Command| Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+        let executor = ScriptedExecutor::new()
+            .expect_with(
+                "curl --fail --silent --show-error --location --output /tool.zip https://example.com/tool.zip",
+                missing,
+            )
+            .expect_with("unzip -q /tool.zip -d /unpacked", missing);
+
+        let e = fetch(
+            &fs,
+            &executor,
+            "https://example.com/tool.zip",
+            Path::new("/tool.zip"),
+        )
+        .unwrap_err();
+        assert_eq!(e.kind().name(), "ArchiveToolNotFound");
+        assert_eq!(e.kind().exit_code(), 31);
+        assert!(e.to_string().starts_with("curl was not found in your path"));
+
+        let e = unpack(
+            &executor,
+            "tool.zip",
+            Path::new("/tool.zip"),
+            Format::Zip,
+            Path::new("/unpacked"),
+        )
+        .unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("unzip was not found in your path"));
+        executor.verify();
+    }
+
+    #[test]
+    fn strip_everything() {
+        let fs = filesystem::FakeFileSystem::new();
+        fs.create_dir_all("/unpacked/tool").unwrap();
+        fs.create_file("/unpacked/tool/tool", "").unwrap();
+
+        assert!(strip_components(&fs, Path::new("/unpacked"), 1, Path::new("/if")).is_ok());
+        assert!(fs.is_file("/if/tool"));
+        assert!(!fs.is_dir("/unpacked"));
+
+        fs.create_dir_all("/unpacked/tool").unwrap();
+        fs.create_file("/unpacked/tool/tool", "").unwrap();
+        assert!(strip_components(&fs, Path::new("/unpacked"), 2, Path::new("/if2")).is_err());
+    }
+}
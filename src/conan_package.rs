@@ -11,7 +11,7 @@ pub struct ConanPackage {
 }
 
 impl ConanPackage {
-    pub fn new(reference: &str) -> err::Result<(Self)> {
+    pub fn new(reference: &str) -> err::Result<Self> {
         let regex = regex::Regex::new(r"^([^/@]+)[/]([^/@]+)(@?$|@([^/@]+)[/]([^/@]+)$)")
             .expect("Conan Package regex was invalid.");
         if !regex.is_match(reference) {
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::err;
+
+/// What happens to the files in the storage once something got installed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionPolicy {
+    /// everything the owner can read or run, all users can too, so a storage can be shared.
+    #[default]
+    AllUsers,
+    /// permissions are left the way conan and git created them.
+    Keep,
+}
+
+/// How a wrapper of another package standing in the way is dealt with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Ask,
+    /// the new package takes the wrapper over.
+    Overwrite,
+    /// the wrapper stays with the package owning it.
+    Keep,
+}
+
+/// Settings and options passed to conan whenever the package is installed.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PackageDefaults {
+    pub settings: Vec<String>,
+    pub options: Vec<String>,
+}
+
+/// Content of `~/.config/cracker/config.toml`. The command line beats the environment variables,
+/// which beat the config, which beats the built-in defaults. e.g.
+///
+/// ```toml
+/// prefix = "/opt/tools"
+/// permissions = "keep"
+/// conflicts = "overwrite"
+///
+/// recipes = ["/srv/recipes", "https://example.com/team/recipes.git"]
+///
+/// [packages.clang-format]
+/// settings = ["compiler.version=9"]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// where the storage and the index live, the home directory if not set.
+    pub prefix: Option<PathBuf>,
+    /// `<prefix>/bin` if not set.
+    pub bin_dir: Option<PathBuf>,
+    pub permissions: PermissionPolicy,
+    pub conflicts: ConflictPolicy,
+    /// keyed by the name of the package.
+    pub packages: BTreeMap<String, PackageDefaults>,
+    /// Directories, or git repositories, with a `<name>/conanfile.py` per recipe. Searched in order
+    /// before the recipes bundled with cracker.
+    pub recipes: Vec<String>,
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `defaults` whose key is not given already, followed by `given`.
+fn merge(defaults: &[String], given: Vec<String>) -> Vec<String> {
+    let key = |s: &str| s.split('=').next().unwrap_or_default().trim().to_owned();
+    let given_keys: Vec<String> = given.iter().map(|g| key(g)).collect();
+    defaults
+        .iter()
+        .filter(|d| !given_keys.contains(&key(d)))
+        .cloned()
+        .chain(given)
+        .collect()
+}
+
+impl Config {
+    /// `CRACKER_CONFIG` if set, `cracker/config.toml` in the XDG config directory otherwise.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("CRACKER_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|c| !c.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|h| h.join(".config")))
+            .map(|c| c.join("cracker").join("config.toml"))
+    }
+
+    /// A missing file is an empty config.
+    pub fn load<Fs: filesystem::FileSystem>(fs: &Fs, path: &Path) -> err::Result<Self> {
+        if !fs.is_file(path) {
+            return Ok(Self::default());
+        }
+
+        let content = fs.read_file_to_string(path)?;
+        toml::from_str(&content)
+            .map_err(|e| err::ErrorKind::ConfigInvalid(path.to_path_buf(), e.to_string()).into())
+    }
+
+    pub(crate) fn prefix(&self, given: Option<PathBuf>) -> Option<PathBuf> {
+        given.or_else(|| self.prefix.clone())
+    }
+
+    pub(crate) fn bin_dir(&self, given: Option<PathBuf>) -> Option<PathBuf> {
+        given.or_else(|| self.bin_dir.clone())
+    }
+
+    /// settings of `package` from the config followed by `given`, which win over the config ones.
+    pub(crate) fn settings(&self, package: &str, given: Vec<String>) -> Vec<String> {
+        match self.packages.get(package) {
+            Some(defaults) => merge(&defaults.settings, given),
+            None => given,
+        }
+    }
+
+    pub(crate) fn options(&self, package: &str, given: Vec<String>) -> Vec<String> {
+        match self.packages.get(package) {
+            Some(defaults) => merge(&defaults.options, given),
+            None => given,
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    #[test]
+    fn load_config() {
+        let fs = FakeFileSystem::new();
+        let path = Path::new("/home/me/.config/cracker/config.toml");
+        assert_eq!(Config::load(&fs, path).unwrap(), Config::default());
+
+        fs.create_dir_all("/home/me/.config/cracker").unwrap();
+        fs.create_file(
+            path,
+            r#"
+prefix = "/opt/tools"
+conflicts = "keep"
+recipes = ["/srv/recipes"]
+
+[packages.abc]
+settings = ["build_type=Debug", "os=Linux"]
+options = ["shared=True"]
+"#,
+        )
+        .unwrap();
+        let config = Config::load(&fs, path).unwrap();
+        assert_eq!(config.prefix, Some(PathBuf::from("/opt/tools")));
+        assert_eq!(config.bin_dir, None);
+        assert_eq!(config.permissions, PermissionPolicy::AllUsers);
+        assert_eq!(config.conflicts, ConflictPolicy::Keep);
+        assert_eq!(config.recipes, vec![String::from("/srv/recipes")]);
+
+        assert_eq!(
+            config.prefix(Some(PathBuf::from("/cli"))),
+            Some(PathBuf::from("/cli"))
+        );
+        assert_eq!(config.prefix(None), Some(PathBuf::from("/opt/tools")));
+        assert_eq!(
+            config.settings("abc", vec![String::from("build_type=Release")]),
+            vec![String::from("os=Linux"), String::from("build_type=Release")]
+        );
+        assert_eq!(
+            config.options("abc", vec![]),
+            vec![String::from("shared=True")]
+        );
+        assert_eq!(
+            config.options("other", vec![String::from("fPIC=True")]),
+            vec![String::from("fPIC=True")]
+        );
+
+        fs.write_file(path, "conflicts = \"sometimes\"").unwrap();
+        let e = Config::load(&fs, path).unwrap_err();
+        assert_eq!(e.kind().name(), "ConfigInvalid");
+    }
+}
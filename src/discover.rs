@@ -0,0 +1,445 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::{err, CrackerFileSystem};
+
+/// Which executables below the search paths get wrappers.
+#[derive(StructOpt, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct OptDiscovery {
+    /// Names or glob patterns of the executables to wrap, e.g. 'clang-*', all of them by default.
+    #[structopt(long)]
+    pub wrappers: Vec<String>,
+    /// How many directories deep executables are looked for below each search path.
+    #[structopt(long, default_value = "1")]
+    pub search_depth: usize,
+    /// Glob patterns of executables not to wrap, e.g. '*-test'.
+    #[structopt(long)]
+    pub exclude: Vec<String>,
+}
+
+/// Same as on the command line: everything directly in the search paths.
+impl Default for OptDiscovery {
+    fn default() -> Self {
+        Self {
+            wrappers: vec![],
+            search_depth: 1,
+            exclude: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// executable bit set, but neither an ELF executable nor a script with a shebang.
+    NotRunnable,
+    Excluded,
+    /// does not match `--wrappers`.
+    NotRequested,
+    /// an executable of the same name was found first.
+    Duplicate,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SkipReason::NotRunnable => "not runnable",
+            SkipReason::Excluded => "excluded",
+            SkipReason::NotRequested => "not requested",
+            SkipReason::Duplicate => "duplicate",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Discovered {
+    pub found: Vec<PathBuf>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// `*` matches any run of characters, `?` any single one, `[abc]`, `[a-z]` and `[!abc]` one out of a set.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+/// Walks both once, on a mismatch the last `*` takes one more character and matching resumes after it.
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // pattern position after the last `*` and name position it resumes from.
+    let mut star = None;
+    while n < name.len() {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            star = Some((p, n));
+        } else if let Some(len) = match_one(&pattern[p..], name[n]) {
+            p += len;
+            n += 1;
+        } else if let Some((after_star, resume)) = star {
+            p = after_star;
+            n = resume + 1;
+            star = Some((after_star, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Length of the pattern element at the start of `pattern` if it matches `c`.
+fn match_one(pattern: &[char], c: char) -> Option<usize> {
+    match pattern.first()? {
+        '*' => None,
+        '?' => Some(1),
+        '[' => match pattern.iter().position(|c| *c == ']') {
+            Some(end) if end > 1 => {
+                let (negated, set) = match pattern[1] {
+                    '!' => (true, &pattern[2..end]),
+                    _ => (false, &pattern[1..end]),
+                };
+                let mut in_set = false;
+                let mut i = 0;
+                while i < set.len() {
+                    if i + 2 < set.len() && set[i + 1] == '-' {
+                        in_set |= set[i] <= c && c <= set[i + 2];
+                        i += 3;
+                    } else {
+                        in_set |= set[i] == c;
+                        i += 1;
+                    }
+                }
+                (in_set != negated).then_some(end + 1)
+            }
+            // not a set, a plain '['.
+            _ => (c == '[').then_some(1),
+        },
+        p => (*p == c).then_some(1),
+    }
+}
+
+/// Whether the first bytes of a file are those of something the system runs: a script with a shebang,
+/// an ELF executable, or a position independent one asking for an interpreter or having an entry point
+/// (static-pie), which shared libraries do not.
+pub(crate) fn is_runnable(head: &[u8]) -> bool {
+    if head.starts_with(b"#!") {
+        return true;
+    }
+    if !head.starts_with(b"\x7fELF") || head.len() < 6 {
+        return false;
+    }
+
+    let is_64 = head[4] == 2;
+    let little_endian = head[5] == 1;
+    let int_at = |offset: usize, len: usize| -> Option<u64> {
+        let bytes = head.get(offset..offset + len)?;
+        let fold = |v: u64, b: &u8| (v << 8) | u64::from(*b);
+        Some(match little_endian {
+            true => bytes.iter().rev().fold(0, fold),
+            false => bytes.iter().fold(0, fold),
+        })
+    };
+    const ET_EXEC: u64 = 2;
+    const ET_DYN: u64 = 3;
+    const PT_INTERP: u64 = 3;
+    let (entry, phoff, phentsize, phnum) = match is_64 {
+        true => (
+            int_at(0x18, 8),
+            int_at(0x20, 8),
+            int_at(0x36, 2),
+            int_at(0x38, 2),
+        ),
+        false => (
+            int_at(0x18, 4),
+            int_at(0x1C, 4),
+            int_at(0x2A, 2),
+            int_at(0x2C, 2),
+        ),
+    };
+
+    match int_at(16, 2) {
+        Some(ET_EXEC) => true,
+        Some(ET_DYN) if entry.is_some_and(|e| e != 0) => true,
+        Some(ET_DYN) => match (phoff, phentsize, phnum) {
+            (Some(phoff), Some(phentsize), Some(phnum)) => (0..phnum).any(|i| {
+                let offset = (phoff + i * phentsize) as usize;
+                int_at(offset, 4) == Some(PT_INTERP)
+            }),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Enough for the ELF header and the program headers following it.
+const HEAD_LEN: usize = 4096;
+
+/// Executables below `root` as selected by `discovery`, shallower ones first so they win over
+/// deeper ones of the same name. Files without the owner executable bit are not even mentioned as skipped.
+pub(crate) fn discover<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    root: &Path,
+    discovery: &OptDiscovery,
+) -> err::Result<Discovered> {
+    let mut discovered = Discovered::default();
+    let mut names: Vec<String> = vec![];
+    let mut files = fs.walk(root, Some(discovery.search_depth))?;
+    files.sort_by_key(|p| p.components().count());
+    for p in files {
+        if !fs.is_file(&p) || fs.is_symlink(&p) || 0o100 & fs.mode(&p)? == 0 {
+            continue;
+        }
+        let name = p.file_name().unwrap_or_default().to_string_lossy();
+        let reason = if !discovery.wrappers.is_empty()
+            && !discovery.wrappers.iter().any(|w| glob_match(w, &name))
+        {
+            Some(SkipReason::NotRequested)
+        } else if discovery.exclude.iter().any(|e| glob_match(e, &name)) {
+            Some(SkipReason::Excluded)
+        } else if names.iter().any(|n| *n == name) {
+            Some(SkipReason::Duplicate)
+        } else if !is_runnable(&fs.read_head(&p, HEAD_LEN)?) {
+            Some(SkipReason::NotRunnable)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => discovered.skipped.push(Skipped { path: p, reason }),
+            None => {
+                names.push(name.into_owned());
+                discovered.found.push(p);
+            }
+        }
+    }
+
+    Ok(discovered)
+}
+
+/// Fails with [`err::ErrorKind::WrappersNotFound`] unless every name or pattern of `--wrappers`
+/// matches an executable below one of `roots`, a typo would wrap nothing otherwise.
+pub(crate) fn check_wrappers<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    roots: &[PathBuf],
+    discovery: &OptDiscovery,
+) -> err::Result<()> {
+    if discovery.wrappers.is_empty() {
+        return Ok(());
+    }
+
+    let everything = OptDiscovery {
+        wrappers: vec![],
+        ..discovery.clone()
+    };
+    let mut available: Vec<String> = vec![];
+    for root in roots {
+        for p in discover(fs, root, &everything)?.found {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            if !available.iter().any(|a| *a == name) {
+                available.push(name.into_owned());
+            }
+        }
+    }
+    let missing: Vec<String> = discovery
+        .wrappers
+        .iter()
+        .filter(|w| !available.iter().any(|a| glob_match(w, a)))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    available.sort();
+    Err(err::ErrorKind::WrappersNotFound(missing, available).into())
+}
+
+#[cfg(test)]
+mod discover_tests {
+    use super::*;
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    /// ELF header of a 64 bit little endian file of `e_type` with one program header of `p_type`.
+    fn elf(e_type: u8, p_type: u8) -> Vec<u8> {
+        let mut head = vec![0u8; 64 + 56];
+        head[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        head[16] = e_type;
+        head[0x20] = 64;
+        head[0x36] = 56;
+        head[0x38] = 1;
+        head[64] = p_type;
+        head
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("clang-*", "clang-format"));
+        assert!(glob_match("clang-*", "clang-"));
+        assert!(!glob_match("clang-*", "clang"));
+        assert!(glob_match("*-test", "abc-test"));
+        assert!(glob_match("lz?", "lz4"));
+        assert!(!glob_match("lz?", "lz"));
+        assert!(glob_match("lz[0-9]c*", "lz4cat"));
+        assert!(!glob_match("lz[!0-9]", "lz4"));
+        assert!(glob_match("a[", "a["));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("*[0-9]", "lz4"));
+        // backtracking to the last star only, no blowup on many of them.
+        let name = "a".repeat(100);
+        assert!(!glob_match("*a*a*a*a*a*a*a*a*b", &name));
+        assert!(glob_match("*a*a*a*a*a*a*a*a*", &name));
+    }
+
+    #[test]
+    fn runnable_files() {
+        assert!(is_runnable(b"#!/bin/sh\necho hi"));
+        assert!(!is_runnable(b"echo hi"));
+        assert!(!is_runnable(b""));
+        // a static executable, a pie one and a shared library.
+        assert!(is_runnable(&elf(2, 1)));
+        assert!(is_runnable(&elf(3, 3)));
+        assert!(!is_runnable(&elf(3, 1)));
+        // a static-pie one, no interpreter but an entry point.
+        let mut static_pie = elf(3, 1);
+        static_pie[0x18] = 0x40;
+        static_pie[0x19] = 0x10;
+        assert!(is_runnable(&static_pie));
+        // relocatable object.
+        assert!(!is_runnable(&elf(1, 0)));
+        assert!(!is_runnable(&elf(3, 3)[..40]));
+
+        let test_binary = std::env::current_exe().unwrap();
+        let fs = filesystem::OsFileSystem::new();
+        assert!(is_runnable(&fs.read_head(&test_binary, HEAD_LEN).unwrap()));
+    }
+
+    #[test]
+    fn discover_executables() {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/pkg/bin/libexec/deeper").unwrap();
+        let files: Vec<(&str, Vec<u8>)> = vec![
+            ("bin/clang-format", elf(3, 3)),
+            ("bin/clang-tidy", b"#!/bin/sh".to_vec()),
+            ("bin/clang-test", b"#!/bin/sh".to_vec()),
+            ("bin/libclang.so", elf(3, 1)),
+            ("bin/README", b"read me".to_vec()),
+            ("bin/opt", b"#!/bin/sh".to_vec()),
+            ("bin/libexec/clang-helper", b"#!/bin/sh".to_vec()),
+            ("bin/libexec/clang-format", b"#!/bin/sh".to_vec()),
+            ("bin/libexec/deeper/clang-deep", b"#!/bin/sh".to_vec()),
+        ];
+        for (name, content) in files.iter() {
+            let path = Path::new("/pkg").join(name);
+            fs.create_file(&path, content).unwrap();
+            if *name != "bin/README" {
+                fs.set_mode(&path, 0o755).unwrap();
+            }
+        }
+
+        let discovered = discover(
+            &fs,
+            Path::new("/pkg/bin"),
+            &OptDiscovery {
+                wrappers: vec![String::from("clang-*")],
+                search_depth: 2,
+                exclude: vec![String::from("*-test")],
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            discovered.found,
+            vec![
+                PathBuf::from("/pkg/bin/clang-format"),
+                PathBuf::from("/pkg/bin/clang-tidy"),
+                PathBuf::from("/pkg/bin/libexec/clang-helper"),
+            ]
+        );
+        let skipped: Vec<(&str, SkipReason)> = discovered
+            .skipped
+            .iter()
+            .map(|s| (s.path.to_str().unwrap(), s.reason))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("/pkg/bin/clang-test", SkipReason::Excluded),
+                ("/pkg/bin/libclang.so", SkipReason::NotRequested),
+                ("/pkg/bin/opt", SkipReason::NotRequested),
+                ("/pkg/bin/libexec/clang-format", SkipReason::Duplicate),
+            ]
+        );
+
+        let discovered = discover(&fs, Path::new("/pkg/bin"), &OptDiscovery::default()).unwrap();
+        assert_eq!(discovered.found.len(), 4);
+        assert!(discovered.skipped.contains(&Skipped {
+            path: PathBuf::from("/pkg/bin/libclang.so"),
+            reason: SkipReason::NotRunnable,
+        }));
+    }
+
+    #[test]
+    fn requested_wrappers_have_to_exist() {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/pkg/bin").unwrap();
+        fs.create_dir_all("/pkg/scripts").unwrap();
+        for name in ["bin/lz4", "bin/lz4cat", "scripts/unlz4"] {
+            let path = Path::new("/pkg").join(name);
+            fs.create_file(&path, "#!/bin/sh").unwrap();
+            fs.set_mode(&path, 0o755).unwrap();
+        }
+        let roots = [PathBuf::from("/pkg/bin"), PathBuf::from("/pkg/scripts")];
+        let requested = |wrappers: &[&str]| OptDiscovery {
+            wrappers: wrappers.iter().map(|w| w.to_string()).collect(),
+            ..Default::default()
+        };
+
+        check_wrappers(&fs, &roots, &requested(&[])).unwrap();
+        check_wrappers(&fs, &roots, &requested(&["lz4", "unlz4", "lz4*"])).unwrap();
+        let e = check_wrappers(&fs, &roots, &requested(&["lz4", "lz5", "x*"])).unwrap_err();
+        match e.kind() {
+            err::ErrorKind::WrappersNotFound(missing, available) => {
+                assert_eq!(missing, &vec![String::from("lz5"), String::from("x*")]);
+                assert_eq!(
+                    available,
+                    &vec![
+                        String::from("lz4"),
+                        String::from("lz4cat"),
+                        String::from("unlz4")
+                    ]
+                );
+            }
+            _ => panic!("unexpected error: {}", e),
+        }
+        assert_eq!(
+            e.to_string(),
+            "no executable matches wrapper(s): lz5, x*, available: lz4, lz4cat, unlz4"
+        );
+
+        // excluded executables are not available.
+        let e = check_wrappers(
+            &fs,
+            &roots,
+            &OptDiscovery {
+                exclude: vec![String::from("un*")],
+                ..requested(&["unlz4"])
+            },
+        )
+        .unwrap_err();
+        assert_eq!(e.kind().exit_code(), 19);
+    }
+}
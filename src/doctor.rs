@@ -0,0 +1,733 @@
+use std::path::{Path, PathBuf};
+
+use filesystem::{DirEntry, FileSystem};
+use serde::Serialize;
+
+use crate::{
+    err, error, get_storage_path, info, refresh_wrapper, set_storage_path, warn, Cracker,
+    CrackerDatabase, CrackerDatabaseData, CrackerFileSystem, Executor, LinkMode, OptDoctor, Paths,
+    Prompt, WrapperScript,
+};
+
+/// Conan's own default for `storage.path`, used when no backup of the user's value exists.
+const CONAN_DEFAULT_STORAGE_PATH: &str = "./data";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Issue {
+    UsernameMismatch {
+        owned_by: String,
+        called_by: String,
+    },
+    MissingInstallFolder {
+        package: String,
+        install_folder: PathBuf,
+    },
+    MissingActivateScript {
+        package: String,
+        script: PathBuf,
+    },
+    MissingWrapper {
+        wrapper: String,
+    },
+    DanglingWrapper {
+        wrapper: String,
+        target: PathBuf,
+    },
+    /// the binary was replaced, e.g. by a rebuild, the hardlink still is the old one.
+    StaleHardlink {
+        wrapper: String,
+        target: PathBuf,
+    },
+    UnlistedWrapper {
+        path: PathBuf,
+    },
+    /// a wrapper script not in the index running something outside the storage, maybe not ours.
+    ForeignWrapper {
+        path: PathBuf,
+        target: PathBuf,
+    },
+    OrphanedInstallFolder {
+        path: PathBuf,
+    },
+    LeakedStoragePath {
+        storage_path: String,
+    },
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::UsernameMismatch {
+                owned_by,
+                called_by,
+            } => write!(
+                f,
+                "storage owned by: '{}' while you are: '{}'",
+                owned_by, called_by
+            ),
+            Issue::MissingInstallFolder {
+                package,
+                install_folder,
+            } => write!(
+                f,
+                "install folder of {} is missing: {}",
+                package,
+                install_folder.display()
+            ),
+            Issue::MissingActivateScript { package, script } => write!(
+                f,
+                "activate script of {} is missing: {}",
+                package,
+                script.display()
+            ),
+            Issue::MissingWrapper { wrapper } => {
+                write!(
+                    f,
+                    "wrapper {} is in the index but not in the bin dir",
+                    wrapper
+                )
+            }
+            Issue::DanglingWrapper { wrapper, target } => write!(
+                f,
+                "wrapper {} points at missing binary: {}",
+                wrapper,
+                target.display()
+            ),
+            Issue::StaleHardlink { wrapper, target } => write!(
+                f,
+                "wrapper {} is a hardlink to an older version of: {}",
+                wrapper,
+                target.display()
+            ),
+            Issue::UnlistedWrapper { path } => {
+                write!(f, "wrapper {} is not in the index", path.display())
+            }
+            Issue::ForeignWrapper { path, target } => write!(
+                f,
+                "wrapper {} is not in the index and runs {}, outside of the storage",
+                path.display(),
+                target.display()
+            ),
+            Issue::OrphanedInstallFolder { path } => write!(
+                f,
+                "install folder {} does not belong to any package",
+                path.display()
+            ),
+            Issue::LeakedStoragePath { storage_path } => write!(
+                f,
+                "conan storage.path still points at the cracker storage: {}",
+                storage_path
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Diagnosis {
+    #[serde(flatten)]
+    pub issue: Issue,
+    /// the issue as printed.
+    pub message: String,
+    pub fixed: bool,
+}
+
+/// What a doctor run found and repaired, see [`crate::OptDoctor`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct DoctorReport {
+    pub issues: Vec<Diagnosis>,
+}
+
+impl DoctorReport {
+    pub(crate) fn unfixed(&self) -> usize {
+        self.issues.iter().filter(|d| !d.fixed).count()
+    }
+}
+
+/// Directories directly in the storage that hold packages, the hidden ones like `.conan` and `.logs`
+/// are cracker's own.
+pub fn install_folders<Fs: FileSystem>(fs: &Fs, paths: &Paths) -> err::Result<Vec<PathBuf>> {
+    let mut folders = vec![];
+    if !fs.is_dir(paths.storage_dir()) {
+        return Ok(folders);
+    }
+
+    for entry in fs.read_dir(paths.storage_dir())? {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with('.') && fs.is_dir(entry.path()) {
+            folders.push(entry.path());
+        }
+    }
+    folders.sort();
+
+    Ok(folders)
+}
+
+/// What a symlink or a wrapper script in the bin dir runs.
+fn wrapper_target<Fs: CrackerFileSystem>(fs: &Fs, path: &Path) -> Option<PathBuf> {
+    if fs.is_symlink(path) {
+        fs.read_link(path).ok()
+    } else {
+        WrapperScript::read(fs, path).map(|s| s.target)
+    }
+}
+
+pub fn orphaned_install_folders<Fs: FileSystem>(
+    fs: &Fs,
+    paths: &Paths,
+    db: &CrackerDatabase,
+) -> err::Result<Vec<PathBuf>> {
+    Ok(install_folders(fs, paths)?
+        .into_iter()
+        .filter(|f| !db.wrapped.iter().any(|e| Path::new(&e.install_folder) == f))
+        .collect())
+}
+
+/// `conan_storage_path` is the current value of conan's `storage.path`, if conan could be asked.
+pub fn diagnose<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    paths: &Paths,
+    db: &CrackerDatabase,
+    conan_storage_path: Option<&str>,
+) -> err::Result<Vec<Issue>> {
+    let mut issues = vec![];
+
+    let username = whoami::username();
+    if db.storage_owned_by != username {
+        issues.push(Issue::UsernameMismatch {
+            owned_by: db.storage_owned_by.clone(),
+            called_by: username,
+        });
+    }
+
+    for entry in db.wrapped.iter() {
+        let install_folder = PathBuf::from(&entry.install_folder);
+        if !fs.is_dir(&install_folder) {
+            issues.push(Issue::MissingInstallFolder {
+                package: entry.data.describe(),
+                install_folder,
+            });
+        } else if let CrackerDatabaseData::Conan { .. } = entry.data {
+            for script in &["activate_run.sh", "activate.sh"] {
+                let script = install_folder.join(script);
+                if !fs.is_file(&script) {
+                    issues.push(Issue::MissingActivateScript {
+                        package: entry.data.describe(),
+                        script,
+                    });
+                }
+            }
+        }
+
+        for wrapper in entry.wrappers.iter() {
+            let wrapper_path = paths.bin_dir().join(&wrapper.wrapped_bin);
+            let target = if wrapper.shadowed {
+                // only the default version has its wrapper in the bin dir.
+                wrapper.target.clone()
+            } else if fs.is_symlink(&wrapper_path) {
+                fs.read_link(&wrapper_path).ok()
+            } else {
+                match WrapperScript::read(fs, &wrapper_path) {
+                    Some(script) if script.shim => wrapper.target.clone(),
+                    Some(script) => Some(script.target),
+                    None if wrapper.link_mode == LinkMode::Hardlink
+                        && fs.is_file(&wrapper_path) =>
+                    {
+                        match &wrapper.target {
+                            Some(target)
+                                if fs.is_file(target) && !fs.same_file(&wrapper_path, target) =>
+                            {
+                                issues.push(Issue::StaleHardlink {
+                                    wrapper: wrapper.wrapped_bin.clone(),
+                                    target: target.clone(),
+                                });
+                                continue;
+                            }
+                            target => target.clone(),
+                        }
+                    }
+                    None => {
+                        issues.push(Issue::MissingWrapper {
+                            wrapper: wrapper.wrapped_bin.clone(),
+                        });
+                        continue;
+                    }
+                }
+            };
+
+            if let Some(target) = target {
+                if !fs.is_file(&target) {
+                    issues.push(Issue::DanglingWrapper {
+                        wrapper: wrapper.wrapped_bin.clone(),
+                        target,
+                    });
+                }
+            }
+        }
+    }
+
+    if fs.is_dir(paths.bin_dir()) {
+        let mut entries = vec![];
+        for entry in fs.read_dir(paths.bin_dir())? {
+            let entry = entry?;
+            if db
+                .providers(&entry.file_name().to_string_lossy())
+                .is_empty()
+            {
+                entries.push(entry.path());
+            }
+        }
+        entries.sort();
+        for path in entries {
+            // the bin dir may be shared with other tools, only what runs from the storage is surely
+            // ours. hardlinks cannot be told apart from anything else.
+            match wrapper_target(fs, &path) {
+                Some(target) if target.starts_with(paths.storage_dir()) => {
+                    issues.push(Issue::UnlistedWrapper { path })
+                }
+                Some(target) if !fs.is_symlink(&path) => {
+                    issues.push(Issue::ForeignWrapper { path, target })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    issues.extend(
+        orphaned_install_folders(fs, paths, db)?
+            .into_iter()
+            .map(|path| Issue::OrphanedInstallFolder { path }),
+    );
+
+    if let Some(storage_path) = conan_storage_path {
+        if Path::new(storage_path) == paths.conan_storage() {
+            issues.push(Issue::LeakedStoragePath {
+                storage_path: storage_path.to_owned(),
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Returns `false` for issues that cannot be repaired automatically.
+pub fn fix<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    paths: &Paths,
+    db: &mut CrackerDatabase,
+    issue: &Issue,
+) -> err::Result<bool> {
+    match issue {
+        Issue::UsernameMismatch { .. }
+        | Issue::MissingInstallFolder { .. }
+        | Issue::MissingActivateScript { .. }
+        | Issue::ForeignWrapper { .. } => Ok(false),
+        Issue::MissingWrapper { wrapper } => {
+            if let Some(w) = db.wrapped(wrapper) {
+                if w.target.is_some() {
+                    refresh_wrapper(fs, paths, db, wrapper)?;
+                } else {
+                    db.unregister_wrapper(&w);
+                }
+            }
+            Ok(true)
+        }
+        Issue::DanglingWrapper { wrapper, target } => {
+            // wrappers from older indices do not know their target, the one in the bin dir tells.
+            let in_bin_dir = WrapperScript::read(fs, &paths.bin_dir().join(wrapper))
+                .is_some_and(|s| !s.shim && s.target == *target);
+            for e in db.wrapped.iter_mut() {
+                e.wrappers.retain(|w| {
+                    let dangling = match &w.target {
+                        Some(t) => t == target,
+                        None => !w.shadowed && in_bin_dir,
+                    };
+                    w.wrapped_bin != *wrapper || !dangling
+                });
+            }
+            db.set_default(wrapper, None);
+            refresh_wrapper(fs, paths, db, wrapper)?;
+            Ok(true)
+        }
+        Issue::StaleHardlink { wrapper, .. } => {
+            refresh_wrapper(fs, paths, db, wrapper)?;
+            Ok(true)
+        }
+        Issue::UnlistedWrapper { path } => {
+            if !wrapper_target(fs, path).is_some_and(|t| t.starts_with(paths.storage_dir())) {
+                return Ok(false);
+            }
+            fs.remove_file(path)?;
+            Ok(true)
+        }
+        Issue::OrphanedInstallFolder { path } => {
+            fs.remove_dir_all(path)?;
+            Ok(true)
+        }
+        Issue::LeakedStoragePath { .. } => {
+            let backup = paths.storage_path_backup();
+            let original = fs
+                .read_file_to_string(&backup)
+                .map(|s| s.trim().to_owned())
+                .unwrap_or_else(|_| CONAN_DEFAULT_STORAGE_PATH.to_owned());
+            set_storage_path(executor, &original)?;
+            if fs.is_file(&backup) {
+                fs.remove_file(&backup)?;
+            }
+            Ok(true)
+        }
+    }
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem,
+    E: Executor,
+    P: Prompt,
+{
+    /// Checks the storage, the index and the wrappers, repairs what it can with `--fix`.
+    /// Fails with [`err::ErrorKind::DoctorIssues`] holding the report if any issue is left.
+    pub fn doctor(&self, i: OptDoctor) -> err::Result<DoctorReport> {
+        let fs = &self.fs;
+        let paths = self.storage(i.prefix, i.bin_dir);
+        let mut db = if fs.is_file(paths.db_path()) {
+            CrackerDatabase::read(fs, paths.db_path())?
+        } else {
+            CrackerDatabase::new()
+        };
+
+        let conan_storage_path = match get_storage_path(&self.executor) {
+            Ok(storage_path) => Some(storage_path),
+            Err(e) => {
+                warn(&format!(
+                    "unable to ask conan for storage.path, skipped: {}",
+                    e
+                ));
+                None
+            }
+        };
+
+        let issues = diagnose(fs, &paths, &db, conan_storage_path.as_deref())?;
+        if issues.is_empty() {
+            info("no issues found.");
+            return Ok(DoctorReport::default());
+        }
+
+        for issue in issues.iter() {
+            warn(&issue.to_string());
+        }
+
+        let mut report = DoctorReport::default();
+        for issue in issues {
+            let fixed = if !i.fix {
+                false
+            } else {
+                match fix(fs, &self.executor, &paths, &mut db, &issue) {
+                    Ok(true) => {
+                        info(&format!("fixed: {}", issue));
+                        true
+                    }
+                    Ok(false) => {
+                        warn(&format!("cannot be fixed automatically: {}", issue));
+                        false
+                    }
+                    Err(e) => {
+                        error(&format!("failed to fix: {}: {}", issue, e));
+                        false
+                    }
+                }
+            };
+            report.issues.push(Diagnosis {
+                message: issue.to_string(),
+                issue,
+                fixed,
+            });
+        }
+
+        if !i.fix {
+            info("rerun with --fix to repair them.");
+        } else if fs.is_file(paths.db_path()) {
+            db.save(fs, paths.db_path())?;
+        }
+
+        if report.unfixed() == 0 {
+            Ok(report)
+        } else {
+            Err(err::ErrorKind::DoctorIssues(report).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod doctor_tests {
+    use super::*;
+    use crate::conan_package::ConanPackage;
+    use crate::executor::ScriptedExecutor;
+    use crate::{CrackerDatabaseEntry, Wrapper};
+
+    fn wrapper(target: &str) -> String {
+        format!(
+            r#"#!/bin/bash
+{} "${{@}}""#,
+            target
+        )
+    }
+
+    fn setup() -> (filesystem::FakeFileSystem, Paths, CrackerDatabase) {
+        let fs = filesystem::FakeFileSystem::new();
+        let paths = Paths::storage(Some(PathBuf::from("/prefix")), None);
+        fs.create_dir_all("/prefix/bin").unwrap();
+        fs.create_dir_all("/prefix/.cracker_storage/.conan")
+            .unwrap();
+        fs.create_dir_all("/prefix/.cracker_storage/conan_abc/bin")
+            .unwrap();
+        fs.create_file("/prefix/.cracker_storage/conan_abc/activate.sh", "")
+            .unwrap();
+        fs.create_file("/prefix/.cracker_storage/conan_abc/activate_run.sh", "")
+            .unwrap();
+        fs.create_file("/prefix/.cracker_storage/conan_abc/bin/abc", "")
+            .unwrap();
+        fs.create_file(
+            "/prefix/bin/abc",
+            wrapper("/prefix/.cracker_storage/conan_abc/bin/abc"),
+        )
+        .unwrap();
+
+        let db = CrackerDatabase {
+            wrapped: vec![CrackerDatabaseEntry {
+                data: CrackerDatabaseData::Conan {
+                    conan_pkg: ConanPackage::new("abc/321@").unwrap(),
+                    conan_settings: vec![],
+                    conan_options: vec![],
+                    recipe: None,
+                    search_depth: 1,
+                    exclude: vec![],
+                },
+                wrappers: vec![Wrapper {
+                    wrapped_bin: String::from("abc"),
+                    target: None,
+                    shadowed: false,
+                    link_mode: LinkMode::Script,
+                }],
+                install_folder: String::from("/prefix/.cracker_storage/conan_abc"),
+            }],
+            storage_owned_by: whoami::username(),
+        };
+
+        (fs, paths, db)
+    }
+
+    #[test]
+    fn healthy_storage() {
+        let (fs, paths, db) = setup();
+        assert_eq!(diagnose(&fs, &paths, &db, Some("./data")).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn detects_issues() {
+        let (fs, paths, mut db) = setup();
+        db.storage_owned_by = String::from("not_me");
+        fs.remove_file("/prefix/.cracker_storage/conan_abc/bin/abc")
+            .unwrap();
+        fs.remove_file("/prefix/.cracker_storage/conan_abc/activate.sh")
+            .unwrap();
+        fs.create_file(
+            "/prefix/bin/stray",
+            wrapper("/prefix/.cracker_storage/git_gone/stray"),
+        )
+        .unwrap();
+        fs.create_file("/prefix/bin/foreign", wrapper("/somewhere/foreign"))
+            .unwrap();
+        fs.create_file("/prefix/bin/not_ours", "#!/bin/sh\necho hi")
+            .unwrap();
+        fs.create_dir_all("/prefix/.cracker_storage/git_gone")
+            .unwrap();
+        fs.create_dir_all("/prefix/.cracker_storage/.logs").unwrap();
+
+        assert_eq!(
+            diagnose(&fs, &paths, &db, Some("/prefix/.cracker_storage/.conan")).unwrap(),
+            vec![
+                Issue::UsernameMismatch {
+                    owned_by: String::from("not_me"),
+                    called_by: whoami::username(),
+                },
+                Issue::MissingActivateScript {
+                    package: String::from("abc/321@"),
+                    script: PathBuf::from("/prefix/.cracker_storage/conan_abc/activate.sh"),
+                },
+                Issue::DanglingWrapper {
+                    wrapper: String::from("abc"),
+                    target: PathBuf::from("/prefix/.cracker_storage/conan_abc/bin/abc"),
+                },
+                Issue::ForeignWrapper {
+                    path: PathBuf::from("/prefix/bin/foreign"),
+                    target: PathBuf::from("/somewhere/foreign"),
+                },
+                Issue::UnlistedWrapper {
+                    path: PathBuf::from("/prefix/bin/stray"),
+                },
+                Issue::OrphanedInstallFolder {
+                    path: PathBuf::from("/prefix/.cracker_storage/git_gone"),
+                },
+                Issue::LeakedStoragePath {
+                    storage_path: String::from("/prefix/.cracker_storage/.conan"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fixes_wrappers_and_folders() {
+        let (fs, paths, mut db) = setup();
+        fs.remove_file("/prefix/.cracker_storage/conan_abc/bin/abc")
+            .unwrap();
+        fs.create_dir_all("/prefix/.cracker_storage/git_gone")
+            .unwrap();
+        fs.create_file("/prefix/bin/foreign", wrapper("/somewhere/foreign"))
+            .unwrap();
+
+        let issues = diagnose(&fs, &paths, &db, None).unwrap();
+        for issue in issues.iter() {
+            let fixed = fix(&fs, &ScriptedExecutor::new(), &paths, &mut db, issue).unwrap();
+            assert_eq!(fixed, !matches!(issue, Issue::ForeignWrapper { .. }));
+        }
+        // whatever it is, a script running something outside the storage is never removed.
+        let unlisted = Issue::UnlistedWrapper {
+            path: PathBuf::from("/prefix/bin/foreign"),
+        };
+        assert!(!fix(&fs, &ScriptedExecutor::new(), &paths, &mut db, &unlisted).unwrap());
+        assert!(fs.is_file("/prefix/bin/foreign"));
+
+        assert!(!fs.is_file("/prefix/bin/abc"));
+        assert!(!fs.is_dir("/prefix/.cracker_storage/git_gone"));
+        assert!(db.wrapped("abc").is_none());
+        assert!(db.wrapped[0].wrappers.is_empty());
+    }
+
+    #[test]
+    fn shadowed_versions() {
+        let (fs, paths, mut db) = setup();
+        let mut other = db.wrapped[0].clone();
+        other.data = CrackerDatabaseData::Conan {
+            conan_pkg: ConanPackage::new("abc/123@").unwrap(),
+            conan_settings: vec![],
+            conan_options: vec![],
+            recipe: None,
+            search_depth: 1,
+            exclude: vec![],
+        };
+        other.install_folder = String::from("/prefix/.cracker_storage/conan_abc_123");
+        other.wrappers[0].target = Some(PathBuf::from(
+            "/prefix/.cracker_storage/conan_abc_123/bin/abc",
+        ));
+        other.wrappers[0].shadowed = true;
+        fs.create_dir_all(&other.install_folder).unwrap();
+        fs.create_file(format!("{}/activate.sh", other.install_folder), "")
+            .unwrap();
+        fs.create_file(format!("{}/activate_run.sh", other.install_folder), "")
+            .unwrap();
+        db.wrapped.push(other);
+
+        let issues = diagnose(&fs, &paths, &db, None).unwrap();
+        assert_eq!(
+            issues,
+            vec![Issue::DanglingWrapper {
+                wrapper: String::from("abc"),
+                target: PathBuf::from("/prefix/.cracker_storage/conan_abc_123/bin/abc"),
+            }]
+        );
+
+        assert!(fix(&fs, &ScriptedExecutor::new(), &paths, &mut db, &issues[0]).unwrap());
+        assert!(db.wrapped[1].wrappers.is_empty());
+        assert_eq!(db.wrapped("abc").unwrap().target, None);
+        assert!(fs.is_file("/prefix/bin/abc"));
+    }
+
+    #[test]
+    fn symlinked_and_hardlinked_wrappers() {
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = filesystem::TempFileSystem::temp_dir(&fs, "cracker").unwrap();
+        let prefix = filesystem::TempDir::path(&tmp).to_path_buf();
+        let paths = Paths::storage(Some(prefix.clone()), None);
+        let install_folder = paths.storage_dir().join("local_tools");
+        fs.create_dir_all(paths.bin_dir()).unwrap();
+        fs.create_dir_all(install_folder.join("bin")).unwrap();
+        let link = |name: &str, link_mode| {
+            let target = install_folder.join("bin").join(name);
+            fs.create_file(&target, "#!/bin/sh").unwrap();
+            Wrapper {
+                wrapped_bin: name.to_owned(),
+                target: Some(target),
+                shadowed: false,
+                link_mode,
+            }
+        };
+        let mut db = CrackerDatabase {
+            wrapped: vec![CrackerDatabaseEntry {
+                data: CrackerDatabaseData::Local {
+                    pkg_name: String::from("tools"),
+                    path: install_folder.clone(),
+                    copied: true,
+                    search_paths: vec![String::from("bin")],
+                    search_depth: 1,
+                    exclude: vec![],
+                },
+                wrappers: vec![
+                    link("sym", LinkMode::Symlink),
+                    link("hard", LinkMode::Hardlink),
+                ],
+                install_folder: install_folder.to_str().unwrap().to_owned(),
+            }],
+            storage_owned_by: whoami::username(),
+        };
+        for name in &["sym", "hard"] {
+            refresh_wrapper(&fs, &paths, &db, name).unwrap();
+        }
+        // other tools may keep their symlinks in the bin dir.
+        fs.symlink(Path::new("/bin/sh"), &paths.bin_dir().join("sh"))
+            .unwrap();
+        assert_eq!(diagnose(&fs, &paths, &db, None).unwrap(), vec![]);
+
+        let sym = install_folder.join("bin/sym");
+        let hard = install_folder.join("bin/hard");
+        fs.remove_file(&sym).unwrap();
+        fs.remove_file(&hard).unwrap();
+        fs.create_file(&hard, "#!/bin/sh\necho rebuilt").unwrap();
+        fs.create_file(install_folder.join("bin/gone"), "").unwrap();
+        fs.symlink(
+            &install_folder.join("bin/gone"),
+            &paths.bin_dir().join("gone"),
+        )
+        .unwrap();
+        let issues = diagnose(&fs, &paths, &db, None).unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                Issue::DanglingWrapper {
+                    wrapper: String::from("sym"),
+                    target: sym.clone(),
+                },
+                Issue::StaleHardlink {
+                    wrapper: String::from("hard"),
+                    target: hard.clone(),
+                },
+                Issue::UnlistedWrapper {
+                    path: paths.bin_dir().join("gone"),
+                },
+            ]
+        );
+
+        for issue in issues.iter() {
+            assert!(fix(&fs, &ScriptedExecutor::new(), &paths, &mut db, issue).unwrap());
+        }
+        assert!(!fs.is_symlink(&paths.bin_dir().join("sym")));
+        assert!(!fs.is_symlink(&paths.bin_dir().join("gone")));
+        assert!(fs.same_file(&paths.bin_dir().join("hard"), &hard));
+        assert_eq!(db.wrapped[0].wrappers.len(), 1);
+        assert_eq!(diagnose(&fs, &paths, &db, None).unwrap(), vec![]);
+    }
+}
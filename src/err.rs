@@ -9,7 +9,16 @@ error_chain! {
             display("Conan was not found in your path.")
         }
         ConanInstallFailure(o: ::std::process::Output) {
-            display("conan install failed with {} \nstdout:\n{} \nstderr:\n{}", o.status, std::str::from_utf8(&o.stdout).unwrap(), std::str::from_utf8(&o.stderr).unwrap())
+            display("conan install failed with {} \nstdout:\n{} \nstderr:\n{}", o.status, String::from_utf8_lossy(&o.stdout), String::from_utf8_lossy(&o.stderr))
+        }
+        ConanCreateFailure(o: ::std::process::Output) {
+            display("conan create failed with {}", o.status)
+        }
+        ConanStorageSwitchFailure(reason: String) {
+            display("unable to switch conan storage.path: {}", reason)
+        }
+        ConanEnvWithoutPath(file: ::std::path::PathBuf) {
+            display("{} does not set PATH, is it a package without binaries?", file.display())
         }
         CrackerStorageDifferentUsername(owned_by: String, called_by : String) {
             description("Cracker storage owned by different user")
@@ -19,5 +28,208 @@ error_chain! {
         GitUnableToExtractProjectName(url : String) {
             display("unable to extract project name from your url: {}", url)
         }
+        GitCloneFailure(url: String, o: ::std::process::Output) {
+            display("git clone of {} failed with {}:\n{}", url, o.status, String::from_utf8_lossy(&o.stderr))
+        }
+        GitBuildFailure(command: String, o: ::std::process::Output) {
+            display("build command '{}' failed with {}", command, o.status)
+        }
+        WrappersNotFound(missing: Vec<String>, available: Vec<String>) {
+            display("no executable matches wrapper(s): {}, available: {}", missing.join(", "),
+                if available.is_empty() { String::from("none") } else { available.join(", ") })
+        }
+        NonUtf8Path(path: ::std::path::PathBuf) {
+            display("path is not valid utf-8: {}", path.display())
+        }
+        ImportFailure(report: crate::report::ImportReport) {
+            display("{} package(s) failed to import", report.failed.len())
+        }
+        DoctorIssues(report: crate::doctor::DoctorReport) {
+            display("{} issue(s) left unfixed", report.unfixed())
+        }
+        ConfigInvalid(path: ::std::path::PathBuf, reason: String) {
+            display("invalid config {}: {}", path.display(), reason)
+        }
+
+        ArchiveDownloadFailure(source: String, o: ::std::process::Output) {
+            display("download of {} failed with {}:\n{}", source, o.status, String::from_utf8_lossy(&o.stderr))
+        }
+        ArchiveChecksumMismatch(source: String, expected: String, actual: String) {
+            display("sha256 of {} is {}, expected {}", source, actual, expected)
+        }
+        ArchiveUnpackFailure(source: String, o: ::std::process::Output) {
+            display("unpacking {} failed with {}:\n{}", source, o.status, String::from_utf8_lossy(&o.stderr))
+        }
+        ArchiveUnknownFormat(file_name: String, known: Vec<String>) {
+            display("unknown archive format of {}, expected one of: {}", file_name, known.join(", "))
+        }
+        ArchiveChecksumFailure(path: ::std::path::PathBuf, o: ::std::process::Output) {
+            display("unable to compute sha256 of {}: {}", path.display(), String::from_utf8_lossy(&o.stderr).trim())
+        }
+        ArchiveEmptyAfterStrip(components: usize) {
+            display("nothing is left of the archive after stripping {} components", components)
+        }
+
+        PathNotFound(path: String, reason: String) {
+            display("unable to find {}: {}", path, reason)
+        }
+        NotADirectory(path: ::std::path::PathBuf) {
+            display("{} is not a directory", path.display())
+        }
+        UnableToName(path: ::std::path::PathBuf) {
+            display("unable to name {}, use --name", path.display())
+        }
+        RecipeNotFound(name: String, hint: String) {
+            display("no recipe named {}, {}", name, hint)
+        }
+        ProjectFileInvalid(path: ::std::path::PathBuf, line: usize, content: String) {
+            display("{}:{}: expected '<package> <version>', got: '{}'", path.display(), line, content)
+        }
+        NotAWrapper(binary: String) {
+            display("{} is not a wrapper generated by cracker.", binary)
+        }
+        WrapperLinkFailure(wrapper: ::std::path::PathBuf, target: ::std::path::PathBuf, reason: String) {
+            display("unable to link {} to {}: {}", wrapper.display(), target.display(), reason)
+        }
+        ArchiveToolNotFound(tool: String) {
+            display("{} was not found in your path, it is needed to install archives.", tool)
+        }
+    }
+}
+
+impl ErrorKind {
+    /// Process exit code for the error, every kind gets its own so scripts can tell them apart.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Msg(_) => 1,
+            ErrorKind::Io(_) => 2,
+            ErrorKind::SerdeJson(_) => 3,
+            ErrorKind::ConanNotInPath => 4,
+            ErrorKind::ConanInstallFailure(_) => 5,
+            ErrorKind::ConanStorageSwitchFailure(_) => 6,
+            ErrorKind::ConanEnvWithoutPath(_) => 7,
+            ErrorKind::CrackerStorageDifferentUsername(_, _) => 8,
+            ErrorKind::GitUnableToExtractProjectName(_) => 9,
+            ErrorKind::GitCloneFailure(_, _) => 10,
+            ErrorKind::NonUtf8Path(_) => 11,
+            ErrorKind::ImportFailure(_) => 12,
+            ErrorKind::ConfigInvalid(_, _) => 13,
+            ErrorKind::ArchiveDownloadFailure(_, _) => 14,
+            ErrorKind::ArchiveChecksumMismatch(_, _, _) => 15,
+            ErrorKind::ArchiveUnpackFailure(_, _) => 16,
+            ErrorKind::GitBuildFailure(_, _) => 17,
+            ErrorKind::ConanCreateFailure(_) => 18,
+            ErrorKind::WrappersNotFound(_, _) => 19,
+            ErrorKind::DoctorIssues(_) => 20,
+            ErrorKind::PathNotFound(_, _) => 21,
+            ErrorKind::NotADirectory(_) => 22,
+            ErrorKind::UnableToName(_) => 23,
+            ErrorKind::RecipeNotFound(_, _) => 24,
+            ErrorKind::ArchiveUnknownFormat(_, _) => 25,
+            ErrorKind::ArchiveChecksumFailure(_, _) => 26,
+            ErrorKind::ArchiveEmptyAfterStrip(_) => 27,
+            ErrorKind::ProjectFileInvalid(_, _, _) => 28,
+            ErrorKind::NotAWrapper(_) => 29,
+            ErrorKind::WrapperLinkFailure(_, _, _) => 30,
+            ErrorKind::ArchiveToolNotFound(_) => 31,
+            ErrorKind::__Nonexhaustive {} => 1,
+        }
+    }
+
+    /// Name of the variant, used by `--output json`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Msg(_) => "Msg",
+            ErrorKind::Io(_) => "Io",
+            ErrorKind::SerdeJson(_) => "SerdeJson",
+            ErrorKind::ConanNotInPath => "ConanNotInPath",
+            ErrorKind::ConanInstallFailure(_) => "ConanInstallFailure",
+            ErrorKind::ConanStorageSwitchFailure(_) => "ConanStorageSwitchFailure",
+            ErrorKind::ConanEnvWithoutPath(_) => "ConanEnvWithoutPath",
+            ErrorKind::CrackerStorageDifferentUsername(_, _) => "CrackerStorageDifferentUsername",
+            ErrorKind::GitUnableToExtractProjectName(_) => "GitUnableToExtractProjectName",
+            ErrorKind::GitCloneFailure(_, _) => "GitCloneFailure",
+            ErrorKind::NonUtf8Path(_) => "NonUtf8Path",
+            ErrorKind::ImportFailure(_) => "ImportFailure",
+            ErrorKind::ConfigInvalid(_, _) => "ConfigInvalid",
+            ErrorKind::ArchiveDownloadFailure(_, _) => "ArchiveDownloadFailure",
+            ErrorKind::ArchiveChecksumMismatch(_, _, _) => "ArchiveChecksumMismatch",
+            ErrorKind::ArchiveUnpackFailure(_, _) => "ArchiveUnpackFailure",
+            ErrorKind::GitBuildFailure(_, _) => "GitBuildFailure",
+            ErrorKind::ConanCreateFailure(_) => "ConanCreateFailure",
+            ErrorKind::WrappersNotFound(_, _) => "WrappersNotFound",
+            ErrorKind::DoctorIssues(_) => "DoctorIssues",
+            ErrorKind::PathNotFound(_, _) => "PathNotFound",
+            ErrorKind::NotADirectory(_) => "NotADirectory",
+            ErrorKind::UnableToName(_) => "UnableToName",
+            ErrorKind::RecipeNotFound(_, _) => "RecipeNotFound",
+            ErrorKind::ArchiveUnknownFormat(_, _) => "ArchiveUnknownFormat",
+            ErrorKind::ArchiveChecksumFailure(_, _) => "ArchiveChecksumFailure",
+            ErrorKind::ArchiveEmptyAfterStrip(_) => "ArchiveEmptyAfterStrip",
+            ErrorKind::ProjectFileInvalid(_, _, _) => "ProjectFileInvalid",
+            ErrorKind::NotAWrapper(_) => "NotAWrapper",
+            ErrorKind::WrapperLinkFailure(_, _, _) => "WrapperLinkFailure",
+            ErrorKind::ArchiveToolNotFound(_) => "ArchiveToolNotFound",
+            ErrorKind::__Nonexhaustive {} => "Nonexhaustive",
+        }
+    }
+}
+
+/// `path` as a str, cracker writes paths into shell scripts and the index so they have to be utf-8.
+pub fn path_str(path: &::std::path::Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| ErrorKind::NonUtf8Path(path.to_path_buf()).into())
+}
+
+#[cfg(test)]
+mod err_tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_and_names_are_distinct() {
+        let output = crate::executor::output(1, "", "");
+        let kinds = vec![
+            ErrorKind::Msg(String::new()),
+            ErrorKind::Io(::std::io::Error::from(::std::io::ErrorKind::NotFound)),
+            ErrorKind::SerdeJson(serde_json::from_str::<u32>("x").unwrap_err()),
+            ErrorKind::ConanNotInPath,
+            ErrorKind::ConanInstallFailure(output.clone()),
+            ErrorKind::ConanStorageSwitchFailure(String::new()),
+            ErrorKind::ConanEnvWithoutPath(Default::default()),
+            ErrorKind::CrackerStorageDifferentUsername(String::new(), String::new()),
+            ErrorKind::GitUnableToExtractProjectName(String::new()),
+            ErrorKind::GitCloneFailure(String::new(), output.clone()),
+            ErrorKind::NonUtf8Path(Default::default()),
+            ErrorKind::ImportFailure(Default::default()),
+            ErrorKind::ConfigInvalid(Default::default(), String::new()),
+            ErrorKind::ArchiveDownloadFailure(String::new(), output.clone()),
+            ErrorKind::ArchiveChecksumMismatch(String::new(), String::new(), String::new()),
+            ErrorKind::ArchiveUnpackFailure(String::new(), output.clone()),
+            ErrorKind::GitBuildFailure(String::new(), output.clone()),
+            ErrorKind::ConanCreateFailure(output.clone()),
+            ErrorKind::WrappersNotFound(vec![], vec![]),
+            ErrorKind::DoctorIssues(Default::default()),
+            ErrorKind::PathNotFound(String::new(), String::new()),
+            ErrorKind::NotADirectory(Default::default()),
+            ErrorKind::UnableToName(Default::default()),
+            ErrorKind::RecipeNotFound(String::new(), String::new()),
+            ErrorKind::ArchiveUnknownFormat(String::new(), vec![]),
+            ErrorKind::ArchiveChecksumFailure(Default::default(), output),
+            ErrorKind::ArchiveEmptyAfterStrip(0),
+            ErrorKind::ProjectFileInvalid(Default::default(), 0, String::new()),
+            ErrorKind::NotAWrapper(String::new()),
+            ErrorKind::WrapperLinkFailure(Default::default(), Default::default(), String::new()),
+            ErrorKind::ArchiveToolNotFound(String::new()),
+        ];
+        let mut codes: Vec<i32> = kinds.iter().map(|k| k.exit_code()).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), kinds.len());
+        assert!(!codes.contains(&0));
+
+        let mut names: Vec<&str> = kinds.iter().map(|k| k.name()).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), kinds.len());
     }
 }
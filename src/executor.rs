@@ -0,0 +1,266 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::{mpsc, Mutex};
+
+use crate::{debug, info};
+
+/// Runs the subprocesses cracker needs: conan, git and the like.
+pub trait Executor: Sync {
+    fn run(&self, c: Command) -> io::Result<Output>;
+
+    /// Same as `run`, but shows the output while the command prints it, for builds taking a while.
+    fn run_streamed(&self, c: Command) -> io::Result<Output> {
+        self.run(c)
+    }
+}
+
+impl<F: Fn(Command) -> io::Result<Output> + Sync> Executor for F {
+    fn run(&self, c: Command) -> io::Result<Output> {
+        self(c)
+    }
+}
+
+/// Spawns the commands for real, the executor used by [`crate::Cracker::system`].
+pub struct SystemExecutor;
+
+impl Executor for SystemExecutor {
+    fn run(&self, mut c: Command) -> io::Result<Output> {
+        debug(&format!("now invoking: {:?}", c));
+        let output = c.output()?;
+        crate::log::command(&c, &output);
+        Ok(output)
+    }
+
+    fn run_streamed(&self, mut c: Command) -> io::Result<Output> {
+        debug(&format!("now invoking: {:?}", c));
+        let mut child = c
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // the lines are logged by this thread, the log prefix and the install log are per thread.
+        let (sender, receiver) = mpsc::channel();
+        let forward = |stream: Option<Box<dyn Read + Send>>, is_stderr: bool| {
+            let sender = sender.clone();
+            stream.map(|stream| {
+                std::thread::spawn(move || {
+                    for line in BufReader::new(stream).lines() {
+                        match line {
+                            Ok(line) => {
+                                let _ = sender.send((is_stderr, line));
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                })
+            })
+        };
+        let readers = vec![
+            forward(child.stdout.take().map(|s| Box::new(s) as _), false),
+            forward(child.stderr.take().map(|s| Box::new(s) as _), true),
+        ];
+        drop(sender);
+
+        let (mut stdout, mut stderr) = (vec![], vec![]);
+        for (is_stderr, line) in receiver {
+            info(&line);
+            let collected = if is_stderr { &mut stderr } else { &mut stdout };
+            collected.extend(line.as_bytes());
+            collected.push(b'\n');
+        }
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+
+        let output = Output {
+            status: child.wait()?,
+            stdout,
+            stderr,
+        };
+        debug(&format!(
+            "{} exited with {}",
+            command_line(&c),
+            output.status
+        ));
+        Ok(output)
+    }
+}
+
+/// The command as it would be typed in a shell, without any quoting: `conan config get storage.path`.
+pub fn command_line(c: &Command) -> String {
+    std::iter::once(c.get_program())
+        .chain(c.get_args())
+        .map(|a| a.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Output of a process that exited with `code`.
+pub fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+    Output {
+        // the raw wait status keeps the exit code in the second byte.
+        status: ExitStatus::from_raw(code << 8),
+        stdout: stdout.as_bytes().to_vec(),
+        stderr: stderr.as_bytes().to_vec(),
+    }
+}
+
+type Respond = Box<dyn Fn(&Command) -> io::Result<Output> + Send + Sync>;
+
+struct Expectation {
+    command_line: String,
+    respond: Respond,
+}
+
+/// Fake executor for tests: answers the expected command lines with canned outputs and fails every
+/// other command. Each expectation is used up by a single invocation, in whatever order they come.
+#[derive(Default)]
+pub struct ScriptedExecutor {
+    expectations: Mutex<VecDeque<Expectation>>,
+    invocations: Mutex<Vec<String>>,
+}
+
+impl ScriptedExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `command_line` succeeds printing `stdout`.
+    pub fn expect(self, command_line: &str, stdout: &str) -> Self {
+        let out = output(0, stdout, "");
+        self.expect_with(command_line, move |_| Ok(out.clone()))
+    }
+
+    /// `command_line` exits with `code` printing `stderr`.
+    pub fn expect_failure(self, command_line: &str, code: i32, stderr: &str) -> Self {
+        let out = output(code, "", stderr);
+        self.expect_with(command_line, move |_| Ok(out.clone()))
+    }
+
+    /// `command_line` is answered by `respond`, which may also fake the side effects of the command,
+    /// like files conan or git would create.
+    pub fn expect_with<F>(self, command_line: &str, respond: F) -> Self
+    where
+        F: Fn(&Command) -> io::Result<Output> + Send + Sync + 'static,
+    {
+        self.expectations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push_back(Expectation {
+                command_line: command_line.to_owned(),
+                respond: Box::new(respond),
+            });
+        self
+    }
+
+    /// Command lines run so far, in order.
+    pub fn invocations(&self) -> Vec<String> {
+        self.invocations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Panics unless every expected command was run.
+    pub fn verify(&self) {
+        let expectations = self.expectations.lock().unwrap_or_else(|e| e.into_inner());
+        let missing: Vec<&str> = expectations
+            .iter()
+            .map(|e| e.command_line.as_str())
+            .collect();
+        assert!(
+            missing.is_empty(),
+            "expected commands were not run: {:#?}\nran: {:#?}",
+            missing,
+            self.invocations()
+        );
+    }
+}
+
+impl Executor for ScriptedExecutor {
+    fn run(&self, c: Command) -> io::Result<Output> {
+        let line = command_line(&c);
+        self.invocations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(line.clone());
+
+        let expectation = {
+            let mut expectations = self.expectations.lock().unwrap_or_else(|e| e.into_inner());
+            let idx = expectations.iter().position(|e| e.command_line == line);
+            idx.and_then(|idx| expectations.remove(idx))
+        };
+
+        match expectation {
+            Some(expectation) => (expectation.respond)(&c),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("unexpected command: {}", line),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod executor_tests {
+    use super::*;
+
+    fn command(line: &str) -> Command {
+        let mut parts = line.split(' ');
+        let mut c = Command::new(parts.next().unwrap());
+        c.args(parts);
+        c
+    }
+
+    #[test]
+    fn scripted_executor() {
+        let executor = ScriptedExecutor::new()
+            .expect("conan config get storage.path", "./data\n")
+            .expect_failure("git clone nope", 128, "fatal: nope");
+
+        let out = executor.run(command("git clone nope")).unwrap();
+        assert_eq!(out.status.code(), Some(128));
+        assert_eq!(out.stderr, b"fatal: nope");
+
+        let out = executor
+            .run(command("conan config get storage.path"))
+            .unwrap();
+        assert!(out.status.success());
+        assert_eq!(out.stdout, b"./data\n");
+
+        // used up already.
+        assert!(executor
+            .run(command("conan config get storage.path"))
+            .is_err());
+        executor.verify();
+        assert_eq!(
+            executor.invocations(),
+            vec![
+                "git clone nope",
+                "conan config get storage.path",
+                "conan config get storage.path"
+            ]
+        );
+    }
+
+    #[test]
+    fn streamed_output_is_kept() {
+        let mut c = Command::new("sh");
+        c.args(["-c", "echo out; echo err >&2; exit 3"]);
+        let out = SystemExecutor.run_streamed(c).unwrap();
+        assert_eq!(out.status.code(), Some(3));
+        assert_eq!(out.stdout, b"out\n");
+        assert_eq!(out.stderr, b"err\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected commands were not run")]
+    fn scripted_executor_verify() {
+        ScriptedExecutor::new()
+            .expect("conan --version", "")
+            .verify();
+    }
+}
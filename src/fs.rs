@@ -0,0 +1,325 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use filesystem::{
+    DirEntry, FakeFileSystem, FileSystem, MockFileSystem, OsFileSystem, UnixFileSystem,
+};
+
+/// What cracker needs from a filesystem on top of [`FileSystem`]: permissions, walking directories,
+/// canonicalization and atomic writes. Implemented for the real filesystem and the in-memory ones
+/// of the filesystem crate, so every code path can run in tests.
+pub trait CrackerFileSystem: FileSystem {
+    /// Permission bits of `path`, symlinks are followed.
+    fn mode(&self, path: &Path) -> io::Result<u32>;
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()>;
+
+    /// Absolute path of `path` with symlinks, `.` and `..` resolved.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    fn is_symlink(&self, path: &Path) -> bool;
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()>;
+
+    fn hard_link(&self, target: &Path, link: &Path) -> io::Result<()>;
+
+    /// Target of the symlink `path`, as written.
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Whether both paths are links to the same file.
+    fn same_file(&self, a: &Path, b: &Path) -> bool;
+
+    /// `root` and everything below it up to `max_depth` levels deep, parents before their children.
+    /// Symlinked directories are listed but not entered.
+    fn walk(&self, root: &Path, max_depth: Option<usize>) -> io::Result<Vec<PathBuf>> {
+        let mut found = vec![root.to_path_buf()];
+        if max_depth == Some(0) || !self.is_dir(root) || self.is_symlink(root) {
+            return Ok(found);
+        }
+
+        let mut children = vec![];
+        for entry in self.read_dir(root)? {
+            children.push(entry?.path());
+        }
+        children.sort();
+        for child in children {
+            found.extend(self.walk(&child, max_depth.map(|d| d - 1))?);
+        }
+
+        Ok(found)
+    }
+
+    /// At most the first `len` bytes of `path`.
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        let mut head = self.read_file(path)?;
+        head.truncate(len);
+        Ok(head)
+    }
+
+    /// Replaces the content of `path` at once, a crash midway leaves the old content in place.
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        if self.is_file(&tmp) {
+            self.remove_file(&tmp)?;
+        }
+        self.write_file(&tmp, contents)?;
+        self.rename(&tmp, path)
+    }
+}
+
+impl CrackerFileSystem for OsFileSystem {
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        UnixFileSystem::mode(self, path)
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        UnixFileSystem::set_mode(self, path, mode)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        std::fs::symlink_metadata(path)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    fn hard_link(&self, target: &Path, link: &Path) -> io::Result<()> {
+        std::fs::hard_link(target, link)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::read_link(path)
+    }
+
+    fn same_file(&self, a: &Path, b: &Path) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        use std::io::Read;
+        let mut head = vec![];
+        std::fs::File::open(path)?
+            .take(len as u64)
+            .read_to_end(&mut head)?;
+        Ok(head)
+    }
+}
+
+fn no_links() -> io::Error {
+    io::Error::other("links are not supported by in-memory filesystems")
+}
+
+/// `path` made absolute against `current_dir` with `.` and `..` resolved, without touching the disk.
+fn normalize(current_dir: &Path, path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in current_dir.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+impl CrackerFileSystem for FakeFileSystem {
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        UnixFileSystem::mode(self, path)
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        UnixFileSystem::set_mode(self, path, mode)
+    }
+
+    /// there are no symlinks in memory, only the path is normalized.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = normalize(&self.current_dir()?, path);
+        if self.is_file(&path) || self.is_dir(&path) {
+            Ok(path)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            ))
+        }
+    }
+
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn symlink(&self, _target: &Path, _link: &Path) -> io::Result<()> {
+        Err(no_links())
+    }
+
+    fn hard_link(&self, _target: &Path, _link: &Path) -> io::Result<()> {
+        Err(no_links())
+    }
+
+    fn read_link(&self, _path: &Path) -> io::Result<PathBuf> {
+        Err(no_links())
+    }
+
+    fn same_file(&self, a: &Path, b: &Path) -> bool {
+        a == b
+    }
+}
+
+/// The mock knows nothing about permissions, every file is a plain readable one.
+impl CrackerFileSystem for MockFileSystem {
+    fn mode(&self, _path: &Path) -> io::Result<u32> {
+        Ok(0o644)
+    }
+
+    fn set_mode(&self, _path: &Path, _mode: u32) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn symlink(&self, _target: &Path, _link: &Path) -> io::Result<()> {
+        Err(no_links())
+    }
+
+    fn hard_link(&self, _target: &Path, _link: &Path) -> io::Result<()> {
+        Err(no_links())
+    }
+
+    fn read_link(&self, _path: &Path) -> io::Result<PathBuf> {
+        Err(no_links())
+    }
+
+    fn same_file(&self, a: &Path, b: &Path) -> bool {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod fs_tests {
+    use super::*;
+
+    #[test]
+    fn walk_with_depth() {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/root/b/deep").unwrap();
+        fs.create_file("/root/a", "").unwrap();
+        fs.create_file("/root/b/c", "").unwrap();
+        fs.create_file("/root/b/deep/d", "").unwrap();
+
+        let paths = |v: &[&str]| v.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(
+            fs.walk(Path::new("/root"), Some(1)).unwrap(),
+            paths(&["/root", "/root/a", "/root/b"])
+        );
+        assert_eq!(
+            fs.walk(Path::new("/root"), None).unwrap(),
+            paths(&[
+                "/root",
+                "/root/a",
+                "/root/b",
+                "/root/b/c",
+                "/root/b/deep",
+                "/root/b/deep/d"
+            ])
+        );
+        assert_eq!(
+            fs.walk(Path::new("/root/a"), None).unwrap(),
+            paths(&["/root/a"])
+        );
+    }
+
+    #[test]
+    fn walk_does_not_enter_symlinked_dirs() {
+        let fs = OsFileSystem::new();
+        let tmp = filesystem::TempFileSystem::temp_dir(&fs, "cracker").unwrap();
+        let root = filesystem::TempDir::path(&tmp).to_path_buf();
+        fs.create_dir_all(root.join("dir")).unwrap();
+        fs.create_file(root.join("dir/file"), "").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("dir/loop")).unwrap();
+
+        assert_eq!(
+            fs.walk(&root, None).unwrap(),
+            vec![
+                root.clone(),
+                root.join("dir"),
+                root.join("dir/file"),
+                root.join("dir/loop")
+            ]
+        );
+        assert_eq!(
+            fs.canonicalize(&root.join("dir/loop/dir/../dir/file"))
+                .unwrap(),
+            fs.canonicalize(&root.join("dir/file")).unwrap()
+        );
+    }
+
+    #[test]
+    fn links() {
+        let fs = OsFileSystem::new();
+        let tmp = filesystem::TempFileSystem::temp_dir(&fs, "cracker").unwrap();
+        let root = filesystem::TempDir::path(&tmp).to_path_buf();
+        fs.create_file(root.join("file"), "content").unwrap();
+        fs.symlink(&root.join("file"), &root.join("sym")).unwrap();
+        fs.hard_link(&root.join("file"), &root.join("hard"))
+            .unwrap();
+
+        assert!(fs.is_symlink(&root.join("sym")));
+        assert!(!fs.is_symlink(&root.join("hard")));
+        assert_eq!(fs.read_link(&root.join("sym")).unwrap(), root.join("file"));
+        assert!(fs.same_file(&root.join("file"), &root.join("hard")));
+        assert!(fs.same_file(&root.join("file"), &root.join("sym")));
+
+        fs.remove_file(root.join("file")).unwrap();
+        fs.create_file(root.join("file"), "content").unwrap();
+        assert!(!fs.same_file(&root.join("file"), &root.join("hard")));
+        assert!(FakeFileSystem::new()
+            .symlink(&root.join("file"), &root.join("x"))
+            .is_err());
+    }
+
+    #[test]
+    fn fake_canonicalize() {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/a/b").unwrap();
+        fs.set_current_dir("/a").unwrap();
+        assert_eq!(
+            fs.canonicalize(Path::new("b/../b/./")).unwrap(),
+            PathBuf::from("/a/b")
+        );
+        assert!(fs.canonicalize(Path::new("c")).is_err());
+    }
+
+    #[test]
+    fn write_atomic() {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/a").unwrap();
+        fs.create_file("/a/index", "old").unwrap();
+        fs.create_file("/a/index.tmp", "leftover of a crash")
+            .unwrap();
+
+        fs.write_atomic(Path::new("/a/index"), b"new").unwrap();
+        assert_eq!(fs.read_file_to_string("/a/index").unwrap(), "new");
+        assert!(!fs.is_file("/a/index.tmp"));
+    }
+}
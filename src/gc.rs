@@ -0,0 +1,260 @@
+use std::path::{Path, PathBuf};
+
+use filesystem::{DirEntry, FileSystem};
+use serde::Serialize;
+
+use crate::doctor::orphaned_install_folders;
+use crate::{
+    err, info, warn, Cracker, CrackerDatabase, CrackerDatabaseData, CrackerFileSystem, Executor,
+    OptGc, Paths, Prompt,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Collected {
+    pub path: PathBuf,
+    /// bytes freed.
+    pub size: u64,
+}
+
+/// What a gc run removed, nothing when the user declined.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct GcReport {
+    pub removed: Vec<Collected>,
+}
+
+fn subdirs<Fs: FileSystem>(fs: &Fs, dir: &Path) -> err::Result<Vec<PathBuf>> {
+    let mut dirs = vec![];
+    for entry in fs.read_dir(dir)? {
+        let path = entry?.path();
+        if fs.is_dir(&path) {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+
+    Ok(dirs)
+}
+
+/// Every `name/version/user/channel` folder in the conan cache of the storage.
+fn conan_references<Fs: FileSystem>(fs: &Fs, paths: &Paths) -> err::Result<Vec<PathBuf>> {
+    let mut references = vec![];
+    if !fs.is_dir(paths.conan_storage()) {
+        return Ok(references);
+    }
+
+    for name in subdirs(fs, &paths.conan_storage())? {
+        for version in subdirs(fs, &name)? {
+            for user in subdirs(fs, &version)? {
+                references.extend(subdirs(fs, &user)?);
+            }
+        }
+    }
+
+    Ok(references)
+}
+
+/// Conan references no installed package runs with. The virtualenv files generated into the install
+/// folders list the directories of every dependency the binaries need, anything not mentioned there is
+/// not used by any wrapper.
+pub fn unused_conan_references<Fs: FileSystem>(
+    fs: &Fs,
+    paths: &Paths,
+    db: &CrackerDatabase,
+) -> err::Result<Vec<PathBuf>> {
+    let mut environments = vec![];
+    for entry in db.wrapped.iter() {
+        if let CrackerDatabaseData::Conan { .. } = entry.data {
+            if !fs.is_dir(&entry.install_folder) {
+                continue;
+            }
+            for file in fs.read_dir(&entry.install_folder)? {
+                if let Ok(content) = fs.read_file_to_string(file?.path()) {
+                    environments.push(content);
+                }
+            }
+        }
+    }
+
+    Ok(conan_references(fs, paths)?
+        .into_iter()
+        .filter(|reference| {
+            let reference = reference.to_string_lossy();
+            !environments.iter().any(|e| e.contains(reference.as_ref()))
+        })
+        .collect())
+}
+
+pub fn disk_usage<Fs: FileSystem>(fs: &Fs, path: &Path) -> u64 {
+    if !fs.is_dir(path) {
+        return fs.len(path);
+    }
+
+    match fs.read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| disk_usage(fs, &e.path()))
+            .sum(),
+        Err(_) => 0,
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, units[unit])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem,
+    E: Executor,
+    P: Prompt,
+{
+    /// Removes orphaned install folders and conan packages no wrapper uses anymore.
+    pub fn gc(&self, i: OptGc) -> err::Result<GcReport> {
+        let fs = &self.fs;
+        let paths = self.storage(i.prefix, i.bin_dir);
+        let mut report = GcReport::default();
+        if !fs.is_file(paths.db_path()) {
+            info("no cracker storage found, nothing to collect.");
+            return Ok(report);
+        }
+        let db = CrackerDatabase::load(fs, paths.db_path())?;
+
+        let mut garbage = orphaned_install_folders(fs, &paths, &db)?;
+        garbage.extend(unused_conan_references(fs, &paths, &db)?);
+        if garbage.is_empty() {
+            info("nothing to collect.");
+            return Ok(report);
+        }
+
+        let garbage: Vec<Collected> = garbage
+            .into_iter()
+            .map(|path| Collected {
+                size: disk_usage(fs, &path),
+                path,
+            })
+            .collect();
+        for g in garbage.iter() {
+            info(&format!("{:>10} {}", format_size(g.size), g.path.display()));
+        }
+        let total = garbage.iter().map(|g| g.size).sum();
+        info(&format!("{:>10} in total", format_size(total)));
+
+        if !i.yes
+            && !self
+                .prompt
+                .confirm(&format!("Remove {} folder(s)?", garbage.len()))
+        {
+            return Ok(report);
+        }
+
+        for g in garbage {
+            match fs.remove_dir_all(&g.path) {
+                Ok(()) => report.removed.push(g),
+                Err(e) => warn(&format!(
+                    "Failure while removing: {}, continued. {}",
+                    g.path.display(),
+                    e
+                )),
+            }
+        }
+        info("ok removed.");
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod gc_tests {
+    use super::*;
+    use crate::conan_package::ConanPackage;
+    use crate::{CrackerDatabaseEntry, LinkMode, Wrapper};
+
+    #[test]
+    fn finds_unused_conan_references() {
+        let fs = filesystem::FakeFileSystem::new();
+        let paths = Paths::storage(Some(PathBuf::from("/prefix")), None);
+        let storage = "/prefix/.cracker_storage/.conan";
+        fs.create_dir_all(format!("{}/abc/321/_/_/package/1234/bin", storage))
+            .unwrap();
+        fs.create_dir_all(format!("{}/zlib/1.2/_/_/package/5678/lib", storage))
+            .unwrap();
+        fs.create_dir_all(format!("{}/old/1.0/me/stable/package/90/bin", storage))
+            .unwrap();
+        fs.create_dir_all("/prefix/.cracker_storage/conan_abc")
+            .unwrap();
+        fs.create_file(
+            "/prefix/.cracker_storage/conan_abc/environment_run.sh.env",
+            format!(
+                "PATH=\"{s}/abc/321/_/_/package/1234/bin\"${{PATH+:$PATH}}\nLD_LIBRARY_PATH=\"{s}/zlib/1.2/_/_/package/5678/lib\"",
+                s = storage
+            ),
+        )
+        .unwrap();
+
+        let db = CrackerDatabase {
+            wrapped: vec![CrackerDatabaseEntry {
+                data: CrackerDatabaseData::Conan {
+                    conan_pkg: ConanPackage::new("abc/321@").unwrap(),
+                    conan_settings: vec![],
+                    conan_options: vec![],
+                    recipe: None,
+                    search_depth: 1,
+                    exclude: vec![],
+                },
+                wrappers: vec![Wrapper {
+                    wrapped_bin: String::from("abc"),
+                    target: None,
+                    shadowed: false,
+                    link_mode: LinkMode::Script,
+                }],
+                install_folder: String::from("/prefix/.cracker_storage/conan_abc"),
+            }],
+            storage_owned_by: whoami::username(),
+        };
+
+        assert_eq!(
+            unused_conan_references(&fs, &paths, &db).unwrap(),
+            vec![PathBuf::from(format!("{}/old/1.0/me/stable", storage))]
+        );
+
+        let empty = CrackerDatabase {
+            wrapped: vec![],
+            storage_owned_by: whoami::username(),
+        };
+        assert_eq!(
+            unused_conan_references(&fs, &paths, &empty).unwrap().len(),
+            3
+        );
+    }
+
+    #[test]
+    fn disk_usage_test() {
+        let fs = filesystem::FakeFileSystem::new();
+        fs.create_dir_all("/a/b").unwrap();
+        fs.create_file("/a/one", "1234").unwrap();
+        fs.create_file("/a/b/two", "12345678").unwrap();
+        assert_eq!(disk_usage(&fs, Path::new("/a")), 12);
+        assert_eq!(disk_usage(&fs, Path::new("/a/one")), 4);
+    }
+
+    #[test]
+    fn format_size_test() {
+        assert_eq!(format_size(12), "12 B");
+        assert_eq!(format_size(2048), "2.0 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 + 512 * 1024), "5.5 MiB");
+    }
+}
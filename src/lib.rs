@@ -0,0 +1,3689 @@
+//! Wraps binaries of conan packages and git repositories so they can be run from a single bin dir.
+//!
+//! [`Cracker`] runs the same commands as the command line, with the filesystem, the way processes
+//! are spawned and the answers to questions injected:
+//!
+//! ```no_run
+//! let cracker = cracker::Cracker::system();
+//! let mut install = cracker::OptInstall::new("clang-format/9.0.0@");
+//! install.prefix = Some("/opt/tools".into());
+//! cracker.install(install).unwrap();
+//! ```
+
+// error_chain! expands recursively per error kind.
+#![recursion_limit = "256"]
+
+use std::path::{Path, PathBuf};
+
+use std::io::BufRead;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use structopt::StructOpt;
+
+use serde::{Deserialize, Serialize};
+
+#[macro_use]
+extern crate error_chain;
+
+mod archive;
+mod conan_package;
+mod config;
+mod discover;
+mod doctor;
+pub mod err;
+pub mod executor;
+mod fs;
+mod gc;
+mod local;
+pub mod log;
+mod project;
+mod recipe;
+mod report;
+mod shell;
+mod which;
+
+pub use crate::conan_package::ConanPackage;
+pub use crate::config::{Config, ConflictPolicy, PackageDefaults, PermissionPolicy};
+pub use crate::discover::{OptDiscovery, SkipReason, Skipped};
+pub use crate::doctor::{Diagnosis, DoctorReport, Issue};
+pub use crate::executor::{Executor, SystemExecutor};
+pub use crate::fs::CrackerFileSystem;
+pub use crate::gc::{Collected, GcReport};
+pub use crate::log::{debug, error, info, warn};
+use crate::log::{set_log_prefix, InstallLog};
+use crate::recipe::Recipe;
+pub use crate::recipe::{KnownRecipe, RecipesReport};
+pub use crate::report::{
+    CommandOutput, Conflict, ErrorReport, ImportFailure, ImportReport, InstallReport, Resolution,
+};
+pub use crate::shell::Shell;
+pub use crate::which::WhichReport;
+/*
+whats next:
+stream output while invoking commands.
+handle errors from git.
+
+ */
+
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptInstall {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+    pub reference: String,
+    #[structopt(flatten)]
+    pub discovery: OptDiscovery,
+    #[structopt(long, short)]
+    pub settings: Vec<String>,
+    #[structopt(long, short)]
+    pub options: Vec<String>,
+    /// Fail rather than build the package from a known recipe when conan is unable to install it.
+    #[structopt(long)]
+    pub no_build: bool,
+    /// Keep everything the install logs, full conan and git output included, in the storage's logs folder.
+    #[structopt(long)]
+    pub log: bool,
+}
+
+/// Creates a package from a conan recipe in the storage, then installs it as `install` does.
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptBuild {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+    /// Directory with a conanfile.py, or one of the bundled recipes: clang-format, git, llvm, lz4,
+    /// neovim, subversion, texinfo or utf8proc.
+    pub recipe: String,
+    pub version: String,
+    /// Name of the package, the name of the recipe's directory by default.
+    #[structopt(long)]
+    pub name: Option<String>,
+    /// Conan profile of the machine doing the build, build requirements are installed for it.
+    #[structopt(long, default_value = "default")]
+    pub profile: String,
+    #[structopt(flatten)]
+    pub discovery: OptDiscovery,
+    #[structopt(long, short)]
+    pub settings: Vec<String>,
+    #[structopt(long, short)]
+    pub options: Vec<String>,
+    /// Keep everything the install logs, full conan and git output included, in the storage's logs folder.
+    #[structopt(long)]
+    pub log: bool,
+}
+
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptGit {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+    /// Anything git clones: https or ssh URLs, `user@host:path`, `file://` URLs or a path on disk.
+    pub url: String,
+    /// Name of the package, the name of the repository by default.
+    #[structopt(long)]
+    pub name: Option<String>,
+    #[structopt(flatten)]
+    pub discovery: OptDiscovery,
+
+    #[structopt(long, default_value = ".")]
+    pub search_paths: Vec<String>,
+    /// Shell command building the project in the clone before its executables are wrapped,
+    /// 'auto' picks cargo, cmake or make depending on the files in the repository.
+    #[structopt(long)]
+    pub build_cmd: Option<String>,
+    /// Clone the submodules too.
+    #[structopt(long)]
+    pub recursive: bool,
+    /// Number of commits to fetch, or 'full' for the whole history.
+    #[structopt(long, default_value = "1")]
+    pub depth: CloneDepth,
+    /// Check out these directories of the repository only.
+    #[structopt(long)]
+    pub sparse: Vec<String>,
+    /// What the bin dir gets for each executable: 'script', 'symlink' or 'hardlink'.
+    #[structopt(long, default_value = "script")]
+    pub link_mode: LinkMode,
+    /// Keep everything the install logs, full conan and git output included, in the storage's logs folder.
+    #[structopt(long)]
+    pub log: bool,
+}
+
+/// How much history a git clone fetches.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloneDepth {
+    Commits(u32),
+    Full,
+}
+
+impl Default for CloneDepth {
+    fn default() -> Self {
+        CloneDepth::Commits(1)
+    }
+}
+
+impl std::str::FromStr for CloneDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(CloneDepth::Full),
+            _ => match s.parse() {
+                Ok(n) if n > 0 => Ok(CloneDepth::Commits(n)),
+                _ => Err(format!(
+                    "invalid depth: {}, expected a number of commits or 'full'.",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// What gets written into the bin dir for a binary that needs no environment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkMode {
+    /// a bash script running the binary.
+    #[default]
+    Script,
+    /// a symlink to the binary, no shell started on every call.
+    Symlink,
+    /// a hardlink to the binary, the bin dir has to be on the same filesystem as the binary.
+    Hardlink,
+}
+
+impl std::fmt::Display for LinkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LinkMode::Script => "script",
+            LinkMode::Symlink => "symlink",
+            LinkMode::Hardlink => "hardlink",
+        })
+    }
+}
+
+impl std::str::FromStr for LinkMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "script" => Ok(LinkMode::Script),
+            "symlink" => Ok(LinkMode::Symlink),
+            "hardlink" => Ok(LinkMode::Hardlink),
+            _ => Err(format!(
+                "invalid link mode: {}, expected script, symlink or hardlink.",
+                s
+            )),
+        }
+    }
+}
+
+/// Downloads or copies a prebuilt archive, unpacks it and wraps the executables found in its search paths.
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptArchive {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+    /// Path or URL of a .tar, .tar.gz, .tgz, .tar.xz, .tar.bz2 or .zip archive.
+    pub source: String,
+    /// Name of the package, the file name of the archive without its extension by default.
+    #[structopt(long)]
+    pub name: Option<String>,
+    /// Expected sha256 of the archive, nothing gets unpacked if it differs.
+    #[structopt(long)]
+    pub sha256: Option<String>,
+    /// Number of leading path components dropped while unpacking, e.g. the `tool-1.0/` most archives start with.
+    #[structopt(long, default_value = "0")]
+    pub strip_components: usize,
+    #[structopt(flatten)]
+    pub discovery: OptDiscovery,
+
+    #[structopt(long, default_value = ".")]
+    pub search_paths: Vec<String>,
+    /// Keep everything the install logs, full conan and git output included, in the storage's logs folder.
+    #[structopt(long)]
+    pub log: bool,
+}
+
+/// Wraps the executables of a directory already on disk, e.g. a tool built by hand.
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptLocal {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+    pub path: PathBuf,
+    /// Name of the package, the name of the directory by default.
+    #[structopt(long)]
+    pub name: Option<String>,
+    /// Copy the directory into the storage instead of running the binaries in place.
+    #[structopt(long)]
+    pub copy: bool,
+    /// What the bin dir gets for each executable: 'script', 'symlink' or 'hardlink'.
+    #[structopt(long, default_value = "script")]
+    pub link_mode: LinkMode,
+    #[structopt(flatten)]
+    pub discovery: OptDiscovery,
+
+    #[structopt(long, default_value = ".")]
+    pub search_paths: Vec<String>,
+    /// Keep everything the install logs, full conan and git output included, in the storage's logs folder.
+    #[structopt(long)]
+    pub log: bool,
+}
+
+/// Installs every package listed in an index exported from another storage.
+/// Packages already present are skipped and failures do not stop the import,
+/// it never asks questions - the imported index wins over the storage contents.
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptImport {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+
+    pub db_path: PathBuf,
+
+    /// Import only these packages, can be given multiple times.
+    #[structopt(long)]
+    pub only: Vec<String>,
+    /// Do not import these packages, can be given multiple times.
+    #[structopt(long)]
+    pub exclude: Vec<String>,
+    /// Number of packages installed at the same time.
+    #[structopt(long, short, default_value = "1")]
+    pub jobs: usize,
+    /// Keep a log of every install, see 'install --log'.
+    #[structopt(long)]
+    pub log: bool,
+}
+
+#[derive(StructOpt, Debug, Default)]
+#[non_exhaustive]
+pub struct OptDoctor {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+
+    /// Repair what can be repaired: remove broken and unknown wrappers and orphaned
+    /// install folders, restore conan's storage.path.
+    #[structopt(long)]
+    pub fix: bool,
+}
+
+#[derive(StructOpt, Debug, Default)]
+#[non_exhaustive]
+pub struct OptRecipes {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+    /// Pull the git repositories among the recipe sources first.
+    #[structopt(long)]
+    pub update: bool,
+}
+
+#[derive(StructOpt, Debug, Default)]
+#[non_exhaustive]
+pub struct OptGc {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+
+    /// Do not ask for confirmation before removing.
+    #[structopt(long, short)]
+    pub yes: bool,
+}
+
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptWhich {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+
+    /// Name of the wrapper in the bin dir.
+    pub binary: String,
+}
+
+#[derive(StructOpt, Debug, Default)]
+#[non_exhaustive]
+pub struct OptEnv {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+
+    /// One of bash, zsh, fish or tcsh, deduced from $SHELL if not given.
+    #[structopt(long)]
+    pub shell: Option<Shell>,
+}
+
+#[derive(StructOpt, Debug)]
+#[non_exhaustive]
+pub struct OptExec {
+    /// Where the storage and the index live, the home directory by default.
+    #[structopt(long, env = "CRACKER_STORAGE_DIR")]
+    pub prefix: Option<PathBuf>,
+    #[structopt(long, env = "CRACKER_STORAGE_BIN")]
+    pub bin_dir: Option<PathBuf>,
+
+    pub binary: String,
+    /// Arguments passed to the binary.
+    #[structopt(last = true)]
+    pub args: Vec<String>,
+}
+
+impl OptInstall {
+    /// Same as `cracker install <reference>`.
+    pub fn new(reference: impl Into<String>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            reference: reference.into(),
+            discovery: OptDiscovery::default(),
+            settings: vec![],
+            options: vec![],
+            no_build: false,
+            log: false,
+        }
+    }
+
+    fn data(&self, conan_pkg: &ConanPackage, recipe: Option<&Recipe>) -> CrackerDatabaseData {
+        CrackerDatabaseData::Conan {
+            conan_pkg: conan_pkg.clone(),
+            conan_settings: self.settings.clone(),
+            conan_options: self.options.clone(),
+            recipe: recipe.cloned(),
+            search_depth: self.discovery.search_depth,
+            exclude: self.discovery.exclude.clone(),
+        }
+    }
+}
+
+impl OptGit {
+    /// Same as `cracker git <url>`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            url: url.into(),
+            name: None,
+            discovery: OptDiscovery::default(),
+            search_paths: vec![String::from(".")],
+            build_cmd: None,
+            recursive: false,
+            depth: CloneDepth::default(),
+            sparse: vec![],
+            link_mode: LinkMode::default(),
+            log: false,
+        }
+    }
+
+    /// `build_cmd` is the one actually run, 'auto' resolved.
+    fn data(&self, pkg_name: &str, build_cmd: Option<&str>) -> CrackerDatabaseData {
+        CrackerDatabaseData::Git {
+            pkg_name: pkg_name.to_owned(),
+            url: self.url.clone(),
+            label: "unimplemented".to_string(),
+            search_paths: self.search_paths.clone(),
+            build_cmd: build_cmd.map(|c| c.to_owned()),
+            recursive: self.recursive,
+            depth: self.depth,
+            sparse: self.sparse.clone(),
+            search_depth: self.discovery.search_depth,
+            exclude: self.discovery.exclude.clone(),
+        }
+    }
+}
+
+impl OptArchive {
+    /// Same as `cracker archive <source>`.
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            source: source.into(),
+            name: None,
+            sha256: None,
+            strip_components: 0,
+            discovery: OptDiscovery::default(),
+            search_paths: vec![String::from(".")],
+            log: false,
+        }
+    }
+
+    fn data(&self, pkg_name: &str) -> CrackerDatabaseData {
+        CrackerDatabaseData::Archive {
+            pkg_name: pkg_name.to_owned(),
+            source: self.source.clone(),
+            sha256: self.sha256.clone(),
+            strip_components: self.strip_components,
+            search_paths: self.search_paths.clone(),
+            search_depth: self.discovery.search_depth,
+            exclude: self.discovery.exclude.clone(),
+        }
+    }
+}
+
+impl OptLocal {
+    /// Same as `cracker local <path>`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            path: path.into(),
+            name: None,
+            copy: false,
+            link_mode: LinkMode::default(),
+            discovery: OptDiscovery::default(),
+            search_paths: vec![String::from(".")],
+            log: false,
+        }
+    }
+
+    fn data(&self, pkg_name: &str) -> CrackerDatabaseData {
+        CrackerDatabaseData::Local {
+            pkg_name: pkg_name.to_owned(),
+            path: self.path.clone(),
+            copied: self.copy,
+            search_paths: self.search_paths.clone(),
+            search_depth: self.discovery.search_depth,
+            exclude: self.discovery.exclude.clone(),
+        }
+    }
+}
+
+impl OptBuild {
+    /// Same as `cracker build <recipe> <version>`.
+    pub fn new(recipe: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            recipe: recipe.into(),
+            version: version.into(),
+            name: None,
+            profile: String::from("default"),
+            discovery: OptDiscovery::default(),
+            settings: vec![],
+            options: vec![],
+            log: false,
+        }
+    }
+}
+
+impl OptImport {
+    /// Same as `cracker import <db_path>`.
+    pub fn new(db_path: impl Into<PathBuf>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            db_path: db_path.into(),
+            only: vec![],
+            exclude: vec![],
+            jobs: 1,
+            log: false,
+        }
+    }
+}
+
+impl OptWhich {
+    /// Same as `cracker which <binary>`.
+    pub fn new(binary: impl Into<String>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            binary: binary.into(),
+        }
+    }
+}
+
+impl OptExec {
+    /// Same as `cracker exec <binary> -- <args>`.
+    pub fn new(binary: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            prefix: None,
+            bin_dir: None,
+            binary: binary.into(),
+            args,
+        }
+    }
+}
+
+struct Paths {
+    prefix: PathBuf,
+    bin_dir: PathBuf,
+    install_type: InstallationType,
+    pkg_name: String,
+    /// executable the shims call back into.
+    cracker: PathBuf,
+}
+
+enum InstallationType {
+    Conan,
+    Git,
+    Archive,
+    Local,
+}
+
+impl InstallationType {
+    fn format(&self) -> &str {
+        match *self {
+            InstallationType::Conan => "conan",
+            InstallationType::Git => "git",
+            InstallationType::Archive => "archive",
+            InstallationType::Local => "local",
+        }
+    }
+}
+
+impl Paths {
+    /// `prefix` defaults to the home directory, `bin_dir` to `<prefix>/bin`.
+    pub fn new(
+        prefix: Option<PathBuf>,
+        bin_dir: Option<PathBuf>,
+        install_type: InstallationType,
+        pkg_name: &str,
+    ) -> Self {
+        let prefix = prefix.or_else(config::home_dir).unwrap_or_default();
+        // the paths end up in the index and in the wrappers, so they must not depend on the working directory.
+        let prefix = std::path::absolute(&prefix).unwrap_or(prefix);
+        let bin_dir = bin_dir.map(|b| std::path::absolute(&b).unwrap_or(b));
+        Self {
+            bin_dir: bin_dir.unwrap_or(prefix.join("bin")),
+            prefix,
+            install_type,
+            pkg_name: pkg_name.to_owned(),
+            cracker: PathBuf::from("cracker"),
+        }
+    }
+
+    fn with_cracker(mut self, cracker: &Path) -> Self {
+        self.cracker = cracker.to_owned();
+        self
+    }
+
+    fn bin_dir(&self) -> PathBuf {
+        self.bin_dir.clone()
+    }
+
+    fn storage_dir(&self) -> PathBuf {
+        self.prefix.join(".cracker_storage")
+    }
+
+    /// Paths of the storage as a whole, for commands not dealing with a single package.
+    fn storage(prefix: Option<PathBuf>, bin_dir: Option<PathBuf>) -> Self {
+        Self::new(prefix, bin_dir, InstallationType::Conan, "")
+    }
+
+    fn db_path(&self) -> PathBuf {
+        self.prefix.join(".cracker_index")
+    }
+
+    fn conan_storage(&self) -> PathBuf {
+        self.storage_dir().join(".conan")
+    }
+
+    /// Archives being downloaded and unpacked, hidden so it is not taken for an install folder.
+    fn downloads_dir(&self) -> PathBuf {
+        self.storage_dir().join(".downloads")
+    }
+
+    /// Bundled recipes written out for conan to create packages from.
+    fn recipes_dir(&self) -> PathBuf {
+        self.storage_dir().join(".recipes")
+    }
+
+    /// Clones of the recipe sources of the config that are git repositories.
+    fn recipe_sources_dir(&self) -> PathBuf {
+        self.storage_dir().join(".recipe_sources")
+    }
+
+    fn logs_dir(&self) -> PathBuf {
+        self.storage_dir().join(".logs")
+    }
+
+    fn install_log(&self) -> PathBuf {
+        self.logs_dir().join(format!(
+            "{}_{}.log",
+            self.install_type.format(),
+            self.pkg_name
+        ))
+    }
+
+    /// Keeps the user's own `storage.path` while cracker has its storage switched in.
+    fn storage_path_backup(&self) -> PathBuf {
+        self.storage_dir().join(".conan_original_storage_path")
+    }
+
+    fn install_folder(&self) -> PathBuf {
+        //so.. it would be better to actully randomize this - but for now its okayish.
+        //conan packages carry the version in pkg_name so versions can live side by side.
+        self.storage_dir()
+            .join(format!("{}_{}", self.install_type.format(), self.pkg_name))
+    }
+}
+
+struct StorageSwitch {
+    users: usize,
+    original_storage: String,
+    backup: PathBuf,
+}
+
+/// `storage.path` lives in the user's global conan config, so all guards alive at once share a single switch:
+/// the first one sets the cracker storage, the last one dropped restores the original.
+static STORAGE_SWITCH: Mutex<Option<StorageSwitch>> = Mutex::new(None);
+
+struct ConanStorageGuard<'a, Fs: CrackerFileSystem> {
+    fs: &'a Fs,
+    executor: &'a dyn Executor,
+}
+
+/// Runs `conan config ...`, a missing conan and a failing command are both reported as typed errors.
+fn conan_config(executor: &dyn Executor, args: &[&str]) -> err::Result<std::process::Output> {
+    let mut c = Command::new("conan");
+    c.arg("config").args(args);
+    let output = executor.run(c).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => err::Error::from(err::ErrorKind::ConanNotInPath),
+        _ => e.into(),
+    })?;
+    if !output.status.success() {
+        return Err(err::ErrorKind::ConanStorageSwitchFailure(format!(
+            "conan config {} failed with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into());
+    }
+
+    Ok(output)
+}
+
+fn get_storage_path(executor: &dyn Executor) -> err::Result<String> {
+    let output = conan_config(executor, &["get", "storage.path"])?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+fn set_storage_path(executor: &dyn Executor, path: &str) -> err::Result<()> {
+    conan_config(executor, &["set", &format!("storage.path={}", path)]).map(|_| ())
+}
+
+impl<'a, Fs: CrackerFileSystem> ConanStorageGuard<'a, Fs> {
+    pub fn new(fs: &'a Fs, executor: &'a dyn Executor, paths: &Paths) -> err::Result<Self> {
+        let mut switch = STORAGE_SWITCH.lock().unwrap_or_else(|e| e.into_inner());
+        match switch.as_mut() {
+            Some(switch) => switch.users += 1,
+            None => {
+                let original_storage = get_storage_path(executor)?;
+                let conan_storage = paths.conan_storage();
+                let conan_storage = err::path_str(&conan_storage)?;
+                // so a crash in the middle of the installation can be repaired by 'cracker doctor --fix'.
+                let backup = paths.storage_path_backup();
+                if let Err(e) = fs.write_file(&backup, &original_storage) {
+                    warn(&format!(
+                        "Unable to back up storage.path to {}: {}",
+                        backup.display(),
+                        e
+                    ));
+                }
+                if let Err(e) = set_storage_path(executor, conan_storage) {
+                    let _ = fs.remove_file(&backup);
+                    return Err(e);
+                }
+                *switch = Some(StorageSwitch {
+                    users: 1,
+                    original_storage,
+                    backup,
+                });
+            }
+        }
+
+        Ok(Self { fs, executor })
+    }
+}
+
+impl<Fs: CrackerFileSystem> Drop for ConanStorageGuard<'_, Fs> {
+    fn drop(&mut self) {
+        let mut switch = STORAGE_SWITCH.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(s) = switch.as_mut() {
+            s.users -= 1;
+            if s.users == 0 {
+                match set_storage_path(self.executor, &s.original_storage) {
+                    Ok(()) => {
+                        let _ = self.fs.remove_file(&s.backup);
+                    }
+                    // the backup stays for 'cracker doctor --fix'.
+                    Err(e) => error(&format!(
+                        "Unable to restore conan storage.path to {}, run 'cracker doctor --fix': {}",
+                        s.original_storage, e
+                    )),
+                }
+                *switch = None;
+            }
+        }
+    }
+}
+
+struct Conan<'a, Fs: CrackerFileSystem> {
+    fs: &'a Fs,
+    executor: &'a dyn Executor,
+}
+
+impl<'a, Fs: CrackerFileSystem> Conan<'a, Fs> {
+    fn new(fs: &'a Fs, executor: &'a dyn Executor) -> err::Result<Self> {
+        Ok(Self { fs, executor })
+    }
+
+    fn install(
+        &self,
+        conan_pkg: &ConanPackage,
+        paths: &Paths,
+        install_folder: &str,
+        settings: &[String],
+        options: &[String],
+    ) -> err::Result<()> {
+        info(&format!("Installing package: {}", conan_pkg.full()));
+        let _guard = ConanStorageGuard::new(self.fs, self.executor, paths)?;
+        let settings: Vec<&str> = settings
+            .iter()
+            .flat_map(|s| vec!["-s", s.as_ref()])
+            .collect();
+        let options: Vec<&str> = options
+            .iter()
+            .flat_map(|o| vec!["-o", o.as_ref()])
+            .collect();
+        let mut c = Command::new("conan");
+        c.args(["install", &conan_pkg.full()])
+            .args(["-if", install_folder])
+            .args(["-g", "virtualrunenv", "-g", "virtualenv"])
+            .args(&settings)
+            .args(&options);
+
+        let output = self.executor.run(c)?;
+
+        if !output.status.success() {
+            Err(err::ErrorKind::ConanInstallFailure(output).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Builds `conan_pkg` from the recipe in `recipe_dir` into the cracker's conan storage.
+    fn create(
+        &self,
+        conan_pkg: &ConanPackage,
+        paths: &Paths,
+        recipe_dir: &Path,
+        profile: &str,
+        settings: &[String],
+        options: &[String],
+    ) -> err::Result<()> {
+        info(&format!(
+            "Creating package: {} from {}",
+            conan_pkg.full(),
+            recipe_dir.display()
+        ));
+        let _guard = ConanStorageGuard::new(self.fs, self.executor, paths)?;
+        let mut c = Command::new("conan");
+        c.args(["create", err::path_str(recipe_dir)?, &conan_pkg.full()])
+            .args(["-pr:b", profile, "--build", "missing"]);
+        for s in settings {
+            c.args(["-s", s]);
+        }
+        for o in options {
+            c.args(["-o", o]);
+        }
+
+        let output = self.executor.run_streamed(c)?;
+        if !output.status.success() {
+            return Err(err::ErrorKind::ConanCreateFailure(output).into());
+        }
+
+        Ok(())
+    }
+}
+
+fn init_cache<Fs: filesystem::FileSystem>(fs: &Fs, paths: &Paths) -> err::Result<CrackerDatabase> {
+    //we need to load cache here.
+    let db = if fs.is_file(paths.db_path()) {
+        CrackerDatabase::load(fs, paths.db_path())?
+    } else {
+        fs.create_dir_all(paths.storage_dir())?;
+        CrackerDatabase::new()
+    };
+    fs.create_dir_all(paths.bin_dir.clone())?;
+
+    Ok(db)
+}
+
+fn generate_enable_script<Fs: filesystem::FileSystem>(fs: &Fs, paths: &Paths) -> err::Result<()> {
+    let dir = paths
+        .bin_dir
+        .parent()
+        .ok_or("Unable to extract parent path")?;
+
+    for shell in Shell::ALL.iter() {
+        fs.write_file(
+            dir.join(shell.enable_script_name()),
+            shell.enable_script(&paths.bin_dir),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn input<R: BufRead>(reader: &mut R, message: &'_ impl std::fmt::Display) -> bool {
+    loop {
+        log::prompt(&message.to_string());
+        let mut ans = String::new();
+        // nobody is there to answer, play safe.
+        if !matches!(reader.read_line(&mut ans), Ok(n) if n > 0) {
+            return false;
+        }
+
+        let ans = ans.to_lowercase();
+        let ans = ans.trim();
+
+        if ans == "y" || ans == "yes" {
+            return true;
+        } else if ans == "n" || ans == "no" {
+            return false;
+        } else {
+            warn(&format!(
+                "only [y|yes|n|no] is accepted as an answer. you gave: {}",
+                ans
+            ))
+        }
+    }
+}
+
+/// Answers the yes/no questions asked while installing, e.g. whether to overwrite a wrapper.
+pub trait Prompt {
+    fn confirm(&self, message: &str) -> bool;
+
+    /// Asked when a wrapper of another package is in the way, see [`ConflictPolicy`].
+    fn confirm_conflict(&self, message: &str) -> bool {
+        self.confirm(message)
+    }
+}
+
+/// Asks on the terminal.
+pub struct StdinPrompt;
+
+impl Prompt for StdinPrompt {
+    fn confirm(&self, message: &str) -> bool {
+        input(&mut std::io::stdin().lock(), &message)
+    }
+}
+
+/// Says yes to everything.
+pub struct AssumeYes;
+
+impl Prompt for AssumeYes {
+    fn confirm(&self, _message: &str) -> bool {
+        true
+    }
+}
+
+/// Answers the conflicts the way the config says, everything else is up to `prompt`.
+struct PolicyPrompt<'a, P: ?Sized> {
+    prompt: &'a P,
+    conflicts: ConflictPolicy,
+}
+
+impl<P: Prompt + ?Sized> Prompt for PolicyPrompt<'_, P> {
+    fn confirm(&self, message: &str) -> bool {
+        self.prompt.confirm(message)
+    }
+
+    fn confirm_conflict(&self, message: &str) -> bool {
+        let answer = match self.conflicts {
+            ConflictPolicy::Ask => return self.prompt.confirm_conflict(message),
+            ConflictPolicy::Overwrite => true,
+            ConflictPolicy::Keep => false,
+        };
+        info(&format!(
+            "{} {} as set by the conflict policy.",
+            message,
+            if answer { "yes" } else { "no" }
+        ));
+        answer
+    }
+}
+
+/// Reads the answers line by line from given reader, answers no once it runs dry.
+pub struct ReaderPrompt<R> {
+    reader: Mutex<R>,
+}
+
+impl<R: BufRead> ReaderPrompt<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: Mutex::new(reader),
+        }
+    }
+}
+
+impl<R: BufRead> Prompt for ReaderPrompt<R> {
+    fn confirm(&self, message: &str) -> bool {
+        input(
+            &mut *self.reader.lock().unwrap_or_else(|e| e.into_inner()),
+            &message,
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Wrapper {
+    wrapped_bin: String,
+    /// the wrapped binary, missing in indices written by older versions.
+    #[serde(default)]
+    target: Option<PathBuf>,
+    /// another version of the same package owns the wrapper in the bin dir,
+    /// this one is run only when a project pins it.
+    #[serde(default)]
+    shadowed: bool,
+    /// shims and wrappers sourcing a conan environment are scripts whatever the mode.
+    #[serde(default)]
+    link_mode: LinkMode,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum CrackerDatabaseData {
+    Conan {
+        conan_pkg: ConanPackage,
+        conan_settings: Vec<String>,
+        conan_options: Vec<String>,
+        /// set when the package was created from a recipe rather than fetched from a remote.
+        #[serde(default)]
+        recipe: Option<Recipe>,
+        /// how executables were looked for, the command line defaults for older indices.
+        #[serde(default = "default_search_depth")]
+        search_depth: usize,
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+    Git {
+        pkg_name: String,
+        url: String,
+        label: String,
+        search_paths: Vec<String>,
+        /// run in the clone before wrapping, missing in indices written by older versions.
+        #[serde(default)]
+        build_cmd: Option<String>,
+        /// how it was cloned, same defaults as the command line for older indices.
+        #[serde(default)]
+        recursive: bool,
+        #[serde(default)]
+        depth: CloneDepth,
+        #[serde(default)]
+        sparse: Vec<String>,
+        #[serde(default = "default_search_depth")]
+        search_depth: usize,
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+    Archive {
+        pkg_name: String,
+        /// path or URL the archive was installed from.
+        source: String,
+        sha256: Option<String>,
+        strip_components: usize,
+        search_paths: Vec<String>,
+        #[serde(default = "default_search_depth")]
+        search_depth: usize,
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+    Local {
+        pkg_name: String,
+        path: PathBuf,
+        /// whether the install folder holds a copy of `path`, the binaries are run in place otherwise.
+        copied: bool,
+        search_paths: Vec<String>,
+        #[serde(default = "default_search_depth")]
+        search_depth: usize,
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+}
+
+fn default_search_depth() -> usize {
+    OptDiscovery::default().search_depth
+}
+
+impl CrackerDatabaseData {
+    fn name(&self) -> &str {
+        match self {
+            CrackerDatabaseData::Conan { conan_pkg, .. } => &conan_pkg.name,
+            CrackerDatabaseData::Git { pkg_name, .. }
+            | CrackerDatabaseData::Archive { pkg_name, .. }
+            | CrackerDatabaseData::Local { pkg_name, .. } => pkg_name,
+        }
+    }
+
+    fn version(&self) -> Option<&str> {
+        match self {
+            CrackerDatabaseData::Conan { conan_pkg, .. } => Some(&conan_pkg.version),
+            CrackerDatabaseData::Git { .. }
+            | CrackerDatabaseData::Archive { .. }
+            | CrackerDatabaseData::Local { .. } => None,
+        }
+    }
+
+    /// How the package was installed finding exactly `wrappers`, to install it the same way again.
+    fn discovery(&self, wrappers: Vec<String>) -> OptDiscovery {
+        let (search_depth, exclude) = match self {
+            CrackerDatabaseData::Conan {
+                search_depth,
+                exclude,
+                ..
+            }
+            | CrackerDatabaseData::Git {
+                search_depth,
+                exclude,
+                ..
+            }
+            | CrackerDatabaseData::Archive {
+                search_depth,
+                exclude,
+                ..
+            }
+            | CrackerDatabaseData::Local {
+                search_depth,
+                exclude,
+                ..
+            } => (*search_depth, exclude.clone()),
+        };
+        OptDiscovery {
+            wrappers,
+            search_depth,
+            exclude,
+        }
+    }
+
+    /// Whether the wrappers have to source the environment generated by conan.
+    fn uses_conan_env(&self) -> bool {
+        match self {
+            CrackerDatabaseData::Conan { .. } => true,
+            CrackerDatabaseData::Git { .. }
+            | CrackerDatabaseData::Archive { .. }
+            | CrackerDatabaseData::Local { .. } => false,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            CrackerDatabaseData::Conan { conan_pkg, .. } => conan_pkg.full(),
+            CrackerDatabaseData::Git { url, .. } => url.clone(),
+            CrackerDatabaseData::Archive { source, .. } => source.clone(),
+            CrackerDatabaseData::Local { path, .. } => path.display().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct CrackerDatabaseEntry {
+    data: CrackerDatabaseData,
+    wrappers: Vec<Wrapper>,
+    install_folder: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct CrackerDatabase {
+    wrapped: Vec<CrackerDatabaseEntry>,
+    storage_owned_by: String,
+}
+
+impl CrackerDatabase {
+    fn new() -> Self {
+        CrackerDatabase {
+            wrapped: vec![],
+            storage_owned_by: whoami::username(),
+        }
+    }
+    /// Reads the index without checking who owns it.
+    fn read<Fs: filesystem::FileSystem>(fs: &Fs, path: PathBuf) -> err::Result<Self> {
+        let content = fs.read_file(path)?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    fn load<Fs: filesystem::FileSystem>(fs: &Fs, path: PathBuf) -> err::Result<Self> {
+        let loaded = Self::read(fs, path)?;
+
+        if loaded.storage_owned_by != whoami::username() {
+            Err(err::ErrorKind::CrackerStorageDifferentUsername(
+                loaded.storage_owned_by,
+                whoami::username(),
+            )
+            .into())
+        } else {
+            Ok(loaded)
+        }
+    }
+
+    fn save<Fs: CrackerFileSystem>(&self, fs: &Fs, path: PathBuf) -> err::Result<()> {
+        let mut sanitized = self.clone();
+        sanitized.wrapped.retain(|e| !e.wrappers.is_empty());
+
+        let ser = serde_json::to_string_pretty(&sanitized)?;
+        // a crash or a concurrent reader never sees a half written index.
+        Ok(fs.write_atomic(&path, ser.as_bytes())?)
+    }
+
+    fn entry(&self, install_folder: &Path) -> Option<&CrackerDatabaseEntry> {
+        self.wrapped
+            .iter()
+            .find(|e| Path::new(&e.install_folder) == install_folder)
+    }
+
+    fn wrapped(&self, wrapper_name: &str) -> Option<Wrapper> {
+        self.owner(wrapper_name)
+            .and_then(|e| e.wrappers.iter().find(|w| w.wrapped_bin == wrapper_name))
+            .cloned()
+    }
+
+    /// The entry of the package that generated given wrapper.
+    fn owner(&self, wrapper_name: &str) -> Option<&CrackerDatabaseEntry> {
+        self.wrapped.iter().find(|e| {
+            e.wrappers
+                .iter()
+                .any(|w| w.wrapped_bin == wrapper_name && !w.shadowed)
+        })
+    }
+
+    /// Every installed package providing given binary, shadowed versions included.
+    fn providers(&self, wrapper_name: &str) -> Vec<(&CrackerDatabaseEntry, &Wrapper)> {
+        self.wrapped
+            .iter()
+            .filter_map(|e| {
+                e.wrappers
+                    .iter()
+                    .find(|w| w.wrapped_bin == wrapper_name)
+                    .map(|w| (e, w))
+            })
+            .collect()
+    }
+
+    /// Makes sure exactly one of the providers owns the wrapper in the bin dir,
+    /// `default_folder` picks the one if given, otherwise the current owner stays.
+    fn set_default(&mut self, wrapper_name: &str, default_folder: Option<&str>) {
+        let default_folder = default_folder
+            .or_else(|| self.owner(wrapper_name).map(|e| e.install_folder.as_str()))
+            .or_else(|| {
+                self.providers(wrapper_name)
+                    .first()
+                    .map(|(e, _)| e.install_folder.as_str())
+            })
+            .map(|f| f.to_owned());
+
+        for e in self.wrapped.iter_mut() {
+            let shadowed = Some(&e.install_folder) != default_folder.as_ref();
+            for w in e.wrappers.iter_mut() {
+                if w.wrapped_bin == wrapper_name {
+                    w.shadowed = shadowed;
+                }
+            }
+        }
+    }
+
+    fn wrappers(&self, install_folder: &str) -> Vec<Wrapper> {
+        self.wrapped
+            .iter()
+            .filter(|e| e.install_folder == install_folder)
+            .map(|e| e.wrappers.clone())
+            .next()
+            .unwrap_or_default()
+    }
+
+    fn register_wrap(&mut self, wrapper: Wrapper, pkg_dir: &str, data: CrackerDatabaseData) {
+        let e_opt = self.wrapped.iter_mut().find(|entry| entry.data == data);
+        let e = if let Some(e) = e_opt {
+            e
+        } else {
+            self.wrapped.push(CrackerDatabaseEntry {
+                data,
+                install_folder: pkg_dir.to_owned(),
+                wrappers: vec![],
+            });
+            self.wrapped.last_mut().unwrap()
+        };
+
+        e.wrappers.push(wrapper);
+    }
+
+    fn unregister_wrapper(&mut self, wrap: &Wrapper) {
+        for e in self.wrapped.iter_mut() {
+            e.wrappers.retain(|w| w != wrap);
+        }
+    }
+
+    fn unregister_pkg(&mut self, install_folder: &str) {
+        self.wrapped.retain(|f| f.install_folder != install_folder);
+    }
+}
+
+struct CrackRequest {
+    bin: PathBuf,
+
+    data: CrackerDatabaseData,
+    link_mode: LinkMode,
+}
+
+fn crack<P: Prompt + ?Sized, Fs: CrackerFileSystem>(
+    prompt: &P,
+    fs: &Fs,
+    request: &CrackRequest,
+    paths: &Paths,
+    db: &mut CrackerDatabase,
+    report: &mut InstallReport,
+) -> err::Result<()> {
+    let bin_name = request
+        .bin
+        .file_name()
+        .ok_or_else(|| format!("{} is not a binary.", request.bin.display()))?;
+    let bin_name = err::path_str(Path::new(bin_name))?.to_owned();
+    info(&format!("Creating wrapper for: {}", bin_name));
+    let wrapper_path = paths.bin_dir.join(&bin_name);
+    let install_folder = paths.install_folder();
+    let install_folder = err::path_str(&install_folder)?;
+    let mut default_folder = Some(install_folder);
+    if let Some(owner) = db.owner(&bin_name) {
+        if owner.data.name() == request.data.name() && owner.install_folder != install_folder {
+            // another version of the same package, both are kept so projects can pick one.
+            let resolution = if prompt.confirm_conflict(&format!(
+                "Wrapper {} is provided by {} already, make {} the default?",
+                bin_name,
+                owner.data.describe(),
+                request.data.describe()
+            )) {
+                report::Resolution::MadeDefault
+            } else {
+                default_folder = None;
+                report::Resolution::KeptDefault
+            };
+            report.conflicts.push(report::Conflict {
+                wrapper: bin_name.clone(),
+                provided_by: owner.data.describe(),
+                resolution,
+            });
+        } else {
+            let overwrite = prompt.confirm_conflict(&format!(
+                "Wrapper {} already generated overwrite?",
+                bin_name
+            ));
+            report.conflicts.push(report::Conflict {
+                wrapper: bin_name.clone(),
+                provided_by: owner.data.describe(),
+                resolution: if overwrite {
+                    report::Resolution::Overwritten
+                } else {
+                    report::Resolution::Kept
+                },
+            });
+            if !overwrite {
+                report.wrappers_skipped.push(bin_name);
+                return Ok(());
+            }
+
+            if let Some(wrapper) = db.wrapped(&bin_name) {
+                fs.remove_file(&wrapper_path)?;
+                db.unregister_wrapper(&wrapper);
+            }
+        }
+    }
+
+    db.register_wrap(
+        Wrapper {
+            wrapped_bin: bin_name.clone(),
+            target: Some(request.bin.clone()),
+            shadowed: false,
+            link_mode: request.link_mode,
+        },
+        install_folder,
+        request.data.clone(),
+    );
+    db.set_default(&bin_name, default_folder);
+
+    refresh_wrapper(fs, paths, db, &bin_name)?;
+    report.wrappers_created.push(bin_name);
+    Ok(())
+}
+
+fn wrapper_script(install_folder: &str, target: &Path, use_conan_env: bool) -> String {
+    if use_conan_env {
+        format!(
+            r#"
+#!/bin/bash
+source {pkg_dir}/activate_run.sh
+source {pkg_dir}/activate.sh
+{bin_name} "${{@}}"
+"#,
+            pkg_dir = install_folder,
+            bin_name = target.display()
+        )
+    } else {
+        format!(
+            r#"
+#!/bin/bash
+{bin_name} "${{@}}"
+"#,
+            bin_name = target.display()
+        )
+    }
+    .trim()
+    .to_owned()
+}
+
+/// Wrapper resolving the version to run from the `.cracker` project files, used when
+/// more than one version of a package provides the binary.
+fn shim_script(paths: &Paths, bin_name: &str) -> String {
+    format!(
+        r#"
+#!/bin/bash
+exec {cracker} exec --prefix {prefix} --bin-dir {bin_dir} {bin_name} -- "${{@}}"
+"#,
+        cracker = paths.cracker.display(),
+        prefix = paths.prefix.display(),
+        bin_dir = paths.bin_dir.display(),
+        bin_name = bin_name
+    )
+    .trim()
+    .to_owned()
+}
+
+/// Writes the wrapper of `bin_name` into the bin dir according to the index:
+/// a plain wrapper or a link for a single provider, a shim for many of them and nothing if there are none.
+fn refresh_wrapper<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    paths: &Paths,
+    db: &CrackerDatabase,
+    bin_name: &str,
+) -> err::Result<()> {
+    let wrapper_path = paths.bin_dir.join(bin_name);
+    let providers = db.providers(bin_name);
+    let wrapper_contents = match providers.as_slice() {
+        [] => return Ok(remove_wrapper(fs, &wrapper_path)?),
+        [(entry, wrapper)] => match &wrapper.target {
+            Some(target) if entry.data.uses_conan_env() => {
+                wrapper_script(&entry.install_folder, target, true)
+            }
+            Some(target) => match wrapper.link_mode {
+                LinkMode::Script => wrapper_script(&entry.install_folder, target, false),
+                link_mode => return link_wrapper(fs, link_mode, target, &wrapper_path),
+            },
+            // written by an older version, the wrapper on disk is all there is.
+            None => return Ok(()),
+        },
+        _ => shim_script(paths, bin_name),
+    };
+
+    // writing into a link would overwrite the binary it points at.
+    remove_wrapper(fs, &wrapper_path)?;
+    fs.write_file(&wrapper_path, wrapper_contents)?;
+    let mode = fs.mode(&wrapper_path)?;
+    fs.set_mode(&wrapper_path, mode | 0o111)?;
+
+    Ok(())
+}
+
+/// Hardlinks do not cross filesystems, a bin dir on another one than the binary gets a symlink.
+fn link_wrapper<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    link_mode: LinkMode,
+    target: &Path,
+    wrapper_path: &Path,
+) -> err::Result<()> {
+    remove_wrapper(fs, wrapper_path)?;
+    let linked = match link_mode {
+        LinkMode::Hardlink => match fs.hard_link(target, wrapper_path) {
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                warn(&format!(
+                    "{} is on another filesystem than {}, symlinked instead of hardlinked.",
+                    wrapper_path.display(),
+                    target.display()
+                ));
+                fs.symlink(target, wrapper_path)
+            }
+            linked => linked,
+        },
+        _ => fs.symlink(target, wrapper_path),
+    };
+
+    linked.map_err(|e| {
+        err::ErrorKind::WrapperLinkFailure(
+            wrapper_path.to_path_buf(),
+            target.to_path_buf(),
+            e.to_string(),
+        )
+        .into()
+    })
+}
+
+/// Removes the link itself rather than what it points at, a dangling symlink included.
+fn remove_wrapper<Fs: CrackerFileSystem>(fs: &Fs, wrapper_path: &Path) -> std::io::Result<()> {
+    if fs.is_file(wrapper_path) || fs.is_symlink(wrapper_path) {
+        fs.remove_file(wrapper_path)?;
+    }
+    Ok(())
+}
+
+/// A wrapper generated by `crack`, read back from the bin dir.
+#[derive(Debug, PartialEq)]
+struct WrapperScript {
+    /// scripts sourced before running the binary.
+    sourced: Vec<PathBuf>,
+    /// for a shim that is cracker itself.
+    target: PathBuf,
+    shim: bool,
+}
+
+impl WrapperScript {
+    fn parse(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        if lines.next()? != "#!/bin/bash" {
+            return None;
+        }
+
+        let mut sourced = vec![];
+        for line in lines {
+            if let Some(script) = line.strip_prefix("source ") {
+                sourced.push(PathBuf::from(script));
+            } else if let Some(target) = line.strip_suffix(r#" "${@}""#) {
+                return Some(match target.strip_prefix("exec ") {
+                    Some(shim) => Self {
+                        sourced,
+                        target: PathBuf::from(shim.split_whitespace().next()?),
+                        shim: true,
+                    },
+                    None => Self {
+                        sourced,
+                        target: PathBuf::from(target),
+                        shim: false,
+                    },
+                });
+            } else {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    fn read<Fs: filesystem::FileSystem>(fs: &Fs, path: &Path) -> Option<Self> {
+        Self::parse(&fs.read_file_to_string(path).ok()?)
+    }
+}
+
+fn expand_mode_to_all_users(mode: u32) -> u32 {
+    let lit = 0o700 & mode;
+    mode | (lit >> 3) | (lit >> 6)
+}
+
+fn bump_permissions<Fs: CrackerFileSystem>(fs: &Fs, path: &Path) -> std::io::Result<()> {
+    let curr_mode = fs.mode(path)?;
+    let expanded_mode = expand_mode_to_all_users(curr_mode);
+    if curr_mode != expanded_mode {
+        fs.set_mode(path, expanded_mode)?;
+    }
+
+    Ok(())
+}
+
+fn extract_path<Fs: filesystem::FileSystem>(fs: &Fs, path: PathBuf) -> err::Result<String> {
+    let content = fs.read_file(&path)?;
+    let content = String::from_utf8_lossy(&content);
+    let regex = regex::Regex::new(r#"^PATH="([^"]+)."#).expect("Path deduction regex was invalid.");
+    content
+        .lines()
+        .find_map(|line| regex.captures(line))
+        .and_then(|captures| captures.get(1))
+        .map(|path| path.as_str().to_owned())
+        .ok_or_else(|| err::ErrorKind::ConanEnvWithoutPath(path).into())
+}
+
+fn preinstall<Fs: filesystem::FileSystem>(fs: &Fs, paths: &Paths) -> err::Result<CrackerDatabase> {
+    let db = init_cache(fs, paths)?;
+
+    generate_enable_script(fs, paths)?;
+
+    Ok(db)
+}
+
+fn bump_storage_permission<Fs: CrackerFileSystem>(fs: &Fs, paths: &Paths) -> err::Result<()> {
+    info("now bumping permissions for all the files.");
+    // symlinks point into the storage anyway, their own mode means nothing.
+    for p in fs.walk(&paths.storage_dir(), None)? {
+        if fs.is_symlink(&p) {
+            continue;
+        }
+        if let Err(e) = bump_permissions(fs, &p) {
+            warn(&format!(
+                "Unable to bump permissions of {}: {}, continued.",
+                p.display(),
+                e
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn make_sure_if_empty<Fs: CrackerFileSystem, P: Prompt + ?Sized>(
+    fs: &Fs,
+    prompt: &P,
+    pkg_name: &str,
+    paths: &Paths,
+    db: &mut CrackerDatabase,
+) -> err::Result<bool> {
+    let if_path = paths.install_folder();
+    if fs.is_dir(&if_path) {
+        let wrappers = db.wrappers(err::path_str(&if_path)?);
+        let bins: Vec<String> = wrappers.iter().map(|w| w.wrapped_bin.clone()).collect();
+        if prompt.confirm(&format!("Package: {} already installed wraps: [{}], to proceed that package has to be removed, remove?", pkg_name, bins.join(", "))) {
+            if let Err(e) = fs.remove_dir_all(&if_path) {
+                warn(&format!("Failure while removing if: {}, continued. {:?}", if_path.display(), e));
+            }
+            db.unregister_pkg(err::path_str(&if_path)?);
+            // other versions of the package may still provide the binaries.
+            for bin in bins {
+                db.set_default(&bin, None);
+                if refresh_wrapper(fs, paths, db, &bin).is_err() {
+                    warn(&format!("Failure while removing wrapper: {}, continued.", bin));
+                }
+            }
+
+            if let Err(e) = db.save(fs, paths.db_path()) {
+                warn(&format!("Failure while saving index: {}, continued.", e));
+            }
+            info("ok removed.");
+            return Ok(true);
+        } else {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Best effort, a failure is only warned about.
+fn remove_install_folder<Fs: CrackerFileSystem>(fs: &Fs, install_folder: &Path) {
+    if fs.is_dir(install_folder) && fs.remove_dir_all(install_folder).is_err() {
+        warn(&format!(
+            "Failure while removing if: {}, continued.",
+            install_folder.display()
+        ));
+    }
+}
+
+/// Makes sure every requested wrapper is there before any gets written. A package without them
+/// is of no use, its install folder goes so trying again starts from scratch.
+fn check_wrappers<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    install_folder: &Path,
+    roots: &[PathBuf],
+    discovery: &OptDiscovery,
+) -> err::Result<()> {
+    let checked = discover::check_wrappers(fs, roots, discovery);
+    if checked.is_err() {
+        remove_install_folder(fs, install_folder);
+    }
+    checked
+}
+
+#[allow(clippy::too_many_arguments)]
+fn crackem<Fs: CrackerFileSystem, P: Prompt + ?Sized>(
+    fs: &Fs,
+    prompt: &P,
+    paths: &Paths,
+    db: &mut CrackerDatabase,
+    root_path: String,
+    discovery: &OptDiscovery,
+    data: CrackerDatabaseData,
+    link_mode: LinkMode,
+) -> err::Result<InstallReport> {
+    let discovered = discover::discover(fs, Path::new(&root_path), discovery)?;
+    let names: Vec<String> = discovered
+        .found
+        .iter()
+        .map(|p| {
+            p.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    info(&format!(
+        "Found {} executable(s) in {}: {}, skipped {}.",
+        names.len(),
+        root_path,
+        names.join(", "),
+        discovered.skipped.len()
+    ));
+    for s in discovered.skipped.iter() {
+        info(&format!("Skipped {} ({}).", s.path.display(), s.reason));
+    }
+
+    let mut report = InstallReport {
+        files_skipped: discovered.skipped,
+        ..Default::default()
+    };
+    for p in discovered.found {
+        crack(
+            prompt,
+            fs,
+            &CrackRequest {
+                bin: fs.canonicalize(&p)?,
+                data: data.clone(),
+                link_mode,
+            },
+            paths,
+            db,
+            &mut report,
+        )?;
+    }
+
+    Ok(report)
+}
+
+/// Same rule as git: `host:path` is scp-like if the colon comes before any slash.
+fn is_scp_like(url: &str) -> bool {
+    !url.contains("://")
+        && url
+            .find(':')
+            .is_some_and(|colon| !url[..colon].contains('/'))
+}
+
+/// Neither a URL nor scp-like, a repository on disk.
+fn is_local_repo(url: &str) -> bool {
+    !url.contains("://") && !is_scp_like(url)
+}
+
+/// Last component of the repository path without `.git`, covers `scheme://[user@]host[:port]/path`,
+/// `[user@]host:path`, `file:///path` and plain paths, bare or not.
+fn extract_git_repo_name(url: &str) -> err::Result<String> {
+    let path = if let Some((_, rest)) = url.split_once("://") {
+        if url.starts_with("file://") {
+            Some(rest)
+        } else {
+            rest.split_once('/').map(|(_host, path)| path)
+        }
+    } else if is_scp_like(url) {
+        url.split_once(':').map(|(_host, path)| path)
+    } else {
+        Some(url)
+    };
+
+    let path = path.unwrap_or_default().trim_end_matches('/');
+    let path = path.strip_suffix("/.git").unwrap_or(path);
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    match name {
+        "" | "." | ".." => {
+            Err(err::ErrorKind::GitUnableToExtractProjectName(url.to_owned()).into())
+        }
+        name => Ok(name.to_owned()),
+    }
+}
+
+fn git_paths(i: &OptGit) -> err::Result<Paths> {
+    let pkg_name = match &i.name {
+        Some(name) => name.clone(),
+        None => extract_git_repo_name(&i.url)?,
+    };
+    Ok(Paths::new(
+        i.prefix.clone(),
+        i.bin_dir.clone(),
+        InstallationType::Git,
+        &pkg_name,
+    ))
+}
+
+fn conan_paths(i: &OptInstall) -> err::Result<Paths> {
+    let conan_pkg = ConanPackage::new(&i.reference)?;
+    Ok(Paths::new(
+        i.prefix.clone(),
+        i.bin_dir.clone(),
+        InstallationType::Conan,
+        &format!("{}_{}", conan_pkg.name, conan_pkg.version),
+    ))
+}
+
+fn lock(db: &Mutex<CrackerDatabase>) -> MutexGuard<'_, CrackerDatabase> {
+    db.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Build command for the project in `folder` judging by its files.
+fn detect_build_cmd<Fs: filesystem::FileSystem>(fs: &Fs, folder: &Path) -> Option<&'static str> {
+    [
+        ("Cargo.toml", "cargo build --release"),
+        (
+            "CMakeLists.txt",
+            "cmake -S . -B build -DCMAKE_BUILD_TYPE=Release && cmake --build build",
+        ),
+        ("Makefile", "make"),
+    ]
+    .iter()
+    .find(|(file, _)| fs.is_file(folder.join(file)))
+    .map(|(_, cmd)| *cmd)
+}
+
+/// Runs `build_cmd` in `folder`, 'auto' is resolved first. Returns the command that was run.
+fn build<Fs: filesystem::FileSystem>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    folder: &Path,
+    build_cmd: &str,
+) -> err::Result<String> {
+    let build_cmd = match build_cmd {
+        "auto" => detect_build_cmd(fs, folder).ok_or_else(|| {
+            format!(
+                "unable to detect how to build {}, no Cargo.toml, CMakeLists.txt or Makefile found",
+                folder.display()
+            )
+        })?,
+        build_cmd => build_cmd,
+    };
+
+    info(&format!("Building: {}", build_cmd));
+    let mut c = Command::new("sh");
+    c.args(["-c", build_cmd]).current_dir(folder);
+    let output = executor.run_streamed(c)?;
+    if !output.status.success() {
+        return Err(err::ErrorKind::GitBuildFailure(build_cmd.to_owned(), output).into());
+    }
+
+    Ok(build_cmd.to_owned())
+}
+
+/// A sparse clone checks out the top level only, its submodules are fetched once the paths are set.
+fn clone(executor: &dyn Executor, i: &OptGit, install_folder: &Path) -> err::Result<()> {
+    let shallow = i.depth != CloneDepth::Full;
+    let sparse = !i.sparse.is_empty();
+    let mut c = Command::new("git");
+    c.args(["clone", &i.url]);
+    if let CloneDepth::Commits(n) = i.depth {
+        c.args(["--depth", &n.to_string()]);
+    }
+    if i.recursive && !sparse {
+        c.arg("--recurse-submodules");
+        if shallow {
+            c.arg("--shallow-submodules");
+        }
+    }
+    if sparse {
+        c.arg("--sparse");
+    }
+    c.arg(err::path_str(install_folder)?);
+    let mut steps = vec![c];
+
+    if sparse {
+        let mut c = Command::new("git");
+        c.args(["sparse-checkout", "set"]).args(&i.sparse);
+        steps.push(c);
+        if i.recursive {
+            let mut c = Command::new("git");
+            c.args(["submodule", "update", "--init", "--recursive"]);
+            if shallow {
+                c.args(["--depth", "1"]);
+            }
+            steps.push(c);
+        }
+    }
+
+    for (n, mut c) in steps.into_iter().enumerate() {
+        if n > 0 {
+            c.current_dir(install_folder);
+        }
+        let output = executor.run(c)?;
+        if !output.status.success() {
+            return Err(err::ErrorKind::GitCloneFailure(i.url.clone(), output).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn install_git<Fs, P>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    prompt: &P,
+    i: &OptGit,
+    paths: &Paths,
+    db: &Mutex<CrackerDatabase>,
+) -> err::Result<InstallReport>
+where
+    Fs: CrackerFileSystem,
+    P: Prompt + ?Sized,
+{
+    let _log = InstallLog::start(fs, i.log.then(|| paths.install_log()));
+    let pkg_name = paths.pkg_name.clone();
+    let mut report = InstallReport::default();
+    if !make_sure_if_empty(fs, prompt, &pkg_name, paths, &mut lock(db))? {
+        warn("Unable to install package.");
+        return Ok(report);
+    }
+
+    let install_folder = paths.install_folder();
+    let cloned = clone(executor, i, &install_folder).and_then(|_| match &i.build_cmd {
+        Some(build_cmd) => build(fs, executor, &install_folder, build_cmd).map(Some),
+        None => Ok(None),
+    });
+    // a clone that is not checked out or does not build is of no use, trying again starts from scratch.
+    let build_cmd = match cloned {
+        Ok(build_cmd) => build_cmd,
+        Err(e) => {
+            remove_install_folder(fs, &install_folder);
+            return Err(e);
+        }
+    };
+
+    let roots: Vec<PathBuf> = i
+        .search_paths
+        .iter()
+        .map(|p| install_folder.join(p))
+        .collect();
+    check_wrappers(fs, &install_folder, &roots, &i.discovery)?;
+    let mut db = lock(db);
+    for path in roots {
+        report.extend(crackem(
+            fs,
+            prompt,
+            paths,
+            &mut db,
+            err::path_str(&path)?.to_owned(),
+            &i.discovery,
+            i.data(&pkg_name, build_cmd.as_deref()),
+            i.link_mode,
+        )?);
+    }
+
+    report.entry = db.entry(&install_folder).cloned();
+    Ok(report)
+}
+
+/// With a `recipe` the package is created from it before being installed.
+fn install_conan<Fs, P>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    prompt: &P,
+    i: &OptInstall,
+    recipe: Option<&Recipe>,
+    paths: &Paths,
+    db: &Mutex<CrackerDatabase>,
+) -> err::Result<InstallReport>
+where
+    Fs: CrackerFileSystem,
+    P: Prompt + ?Sized,
+{
+    let _log = InstallLog::start(fs, i.log.then(|| paths.install_log()));
+    let conan_pkg = ConanPackage::new(&i.reference)?;
+    let conan = Conan::new(fs, executor)?;
+
+    let if_path = paths.install_folder();
+    let mut report = InstallReport::default();
+    if !make_sure_if_empty(fs, prompt, &conan_pkg.name, paths, &mut lock(db))? {
+        warn("Unable to install package.");
+        return Ok(report);
+    }
+
+    let install_folder = err::path_str(&if_path)?;
+    if let Some(recipe) = recipe {
+        // an index from elsewhere may point at a bundled recipe written out in another storage.
+        let recipe_dir = recipe::locate(fs, paths, &recipe.path)?;
+        conan.create(
+            &conan_pkg,
+            paths,
+            &recipe_dir,
+            &recipe.profile,
+            &i.settings,
+            &i.options,
+        )?;
+    }
+    conan.install(&conan_pkg, paths, install_folder, &i.settings, &i.options)?;
+
+    let env_run_path = if_path.join("environment_run.sh.env");
+    let path = extract_path(fs, env_run_path)?;
+
+    check_wrappers(fs, &if_path, &[PathBuf::from(&path)], &i.discovery)?;
+    let mut db = lock(db);
+    report.extend(crackem(
+        fs,
+        prompt,
+        paths,
+        &mut db,
+        path,
+        &i.discovery,
+        i.data(&conan_pkg, recipe),
+        // the environment has to be sourced, there is nothing to link.
+        LinkMode::Script,
+    )?);
+
+    report.entry = db.entry(&if_path).cloned();
+    Ok(report)
+}
+
+/// Checks whether `entry` from an imported index is already present in the storage:
+/// its install folder exists and every one of its wrappers is registered and on disk.
+fn already_installed<Fs: filesystem::FileSystem>(
+    fs: &Fs,
+    paths: &Paths,
+    db: &CrackerDatabase,
+    entry: &CrackerDatabaseEntry,
+) -> bool {
+    let if_path = paths.install_folder();
+    if !fs.is_dir(&if_path) {
+        return false;
+    }
+
+    let installed = db
+        .wrapped
+        .iter()
+        .find(|e| Path::new(&e.install_folder) == if_path && e.data == entry.data);
+
+    match installed {
+        Some(installed) => entry.wrappers.iter().all(|w| {
+            installed
+                .wrappers
+                .iter()
+                .any(|iw| iw.wrapped_bin == w.wrapped_bin)
+                && fs.is_file(paths.bin_dir().join(&w.wrapped_bin))
+        }),
+        None => false,
+    }
+}
+
+enum ImportInstall {
+    Conan(OptInstall, Option<Recipe>),
+    Git(OptGit),
+    Archive(OptArchive),
+    Local(OptLocal),
+}
+
+struct ImportJob {
+    /// the imported entry, with the data as this installation is going to register it.
+    entry: CrackerDatabaseEntry,
+    install: ImportInstall,
+    paths: Paths,
+}
+
+impl ImportJob {
+    fn new(i: &OptImport, entry: &CrackerDatabaseEntry) -> err::Result<Self> {
+        let discovery = entry.data.discovery(
+            entry
+                .wrappers
+                .iter()
+                .map(|w| w.wrapped_bin.clone())
+                .collect(),
+        );
+        let link_mode = entry
+            .wrappers
+            .first()
+            .map(|w| w.link_mode)
+            .unwrap_or_default();
+        let (install, paths, data) = match &entry.data {
+            CrackerDatabaseData::Conan {
+                conan_pkg,
+                conan_options,
+                conan_settings,
+                recipe,
+                ..
+            } => {
+                let install = OptInstall {
+                    prefix: i.prefix.clone(),
+                    bin_dir: i.bin_dir.clone(),
+                    options: conan_options.clone(),
+                    settings: conan_settings.clone(),
+                    discovery,
+                    reference: conan_pkg.full(),
+                    // the index tells whether it was built from a recipe.
+                    no_build: true,
+                    log: i.log,
+                };
+                let paths = conan_paths(&install)?;
+                let data = install.data(conan_pkg, recipe.as_ref());
+                (ImportInstall::Conan(install, recipe.clone()), paths, data)
+            }
+            CrackerDatabaseData::Git {
+                pkg_name,
+                url,
+                search_paths,
+                build_cmd,
+                recursive,
+                depth,
+                sparse,
+                ..
+            } => {
+                let install = OptGit {
+                    url: url.clone(),
+                    name: Some(pkg_name.clone()),
+                    recursive: *recursive,
+                    depth: *depth,
+                    sparse: sparse.clone(),
+                    link_mode,
+                    search_paths: search_paths.clone(),
+                    build_cmd: build_cmd.clone(),
+                    discovery,
+                    prefix: i.prefix.clone(),
+                    bin_dir: i.bin_dir.clone(),
+                    log: i.log,
+                };
+                let paths = git_paths(&install)?;
+                let data = install.data(&paths.pkg_name, install.build_cmd.as_deref());
+                (ImportInstall::Git(install), paths, data)
+            }
+            CrackerDatabaseData::Archive {
+                pkg_name,
+                source,
+                sha256,
+                strip_components,
+                search_paths,
+                ..
+            } => {
+                let install = OptArchive {
+                    prefix: i.prefix.clone(),
+                    bin_dir: i.bin_dir.clone(),
+                    source: source.clone(),
+                    name: Some(pkg_name.clone()),
+                    sha256: sha256.clone(),
+                    strip_components: *strip_components,
+                    discovery,
+                    search_paths: search_paths.clone(),
+                    log: i.log,
+                };
+                let paths = archive::archive_paths(&install)?;
+                let data = install.data(&paths.pkg_name);
+                (ImportInstall::Archive(install), paths, data)
+            }
+            CrackerDatabaseData::Local {
+                pkg_name,
+                path,
+                copied,
+                search_paths,
+                ..
+            } => {
+                let install = OptLocal {
+                    prefix: i.prefix.clone(),
+                    bin_dir: i.bin_dir.clone(),
+                    path: path.clone(),
+                    name: Some(pkg_name.clone()),
+                    copy: *copied,
+                    link_mode,
+                    discovery,
+                    search_paths: search_paths.clone(),
+                    log: i.log,
+                };
+                let paths = local::local_paths(&install)?;
+                let data = install.data(&paths.pkg_name);
+                (ImportInstall::Local(install), paths, data)
+            }
+        };
+
+        Ok(Self {
+            entry: CrackerDatabaseEntry {
+                data,
+                ..entry.clone()
+            },
+            install,
+            paths,
+        })
+    }
+
+    /// `None` means the package was already there.
+    fn run<Fs: CrackerFileSystem, P: Prompt + ?Sized>(
+        &self,
+        fs: &Fs,
+        executor: &dyn Executor,
+        prompt: &P,
+        db: &Mutex<CrackerDatabase>,
+    ) -> err::Result<Option<InstallReport>> {
+        if already_installed(fs, &self.paths, &lock(db), &self.entry) {
+            return Ok(None);
+        }
+
+        let result = match &self.install {
+            ImportInstall::Conan(install, recipe) => install_conan(
+                fs,
+                executor,
+                prompt,
+                install,
+                recipe.as_ref(),
+                &self.paths,
+                db,
+            ),
+            ImportInstall::Git(install) => {
+                install_git(fs, executor, prompt, install, &self.paths, db)
+            }
+            ImportInstall::Archive(install) => {
+                archive::install_archive(fs, executor, prompt, install, &self.paths, db)
+            }
+            ImportInstall::Local(install) => {
+                local::install_local(fs, prompt, install, &self.paths, db)
+            }
+        };
+        lock(db).save(fs, self.paths.db_path())?;
+        result.map(Some)
+    }
+}
+
+/// Runs the jobs on up to `jobs` threads. Jobs sharing an install folder are run one after another
+/// on the same thread as they would otherwise trample on each other.
+fn run_import_jobs<Fs: CrackerFileSystem + Sync, P: Prompt + Sync>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    prompt: &P,
+    import_jobs: &[ImportJob],
+    db: &Mutex<CrackerDatabase>,
+    jobs: usize,
+) -> Vec<err::Result<Option<InstallReport>>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for (idx, job) in import_jobs.iter().enumerate() {
+        let if_path = job.paths.install_folder();
+        match groups
+            .iter_mut()
+            .find(|g| import_jobs[g[0]].paths.install_folder() == if_path)
+        {
+            Some(group) => group.push(idx),
+            None => groups.push(vec![idx]),
+        }
+    }
+
+    let next_group = AtomicUsize::new(0);
+    let outcomes: Vec<Mutex<Option<err::Result<Option<InstallReport>>>>> =
+        import_jobs.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, groups.len().max(1)) {
+            s.spawn(|| {
+                while let Some(group) = groups.get(next_group.fetch_add(1, Ordering::SeqCst)) {
+                    for &idx in group {
+                        let job = &import_jobs[idx];
+                        set_log_prefix(&format!("[{}] ", job.entry.data.name()));
+                        info(&format!("now installing: {}", job.entry.data.describe()));
+                        let outcome = job.run(fs, executor, prompt, db);
+                        match &outcome {
+                            Ok(Some(_)) => info("installed."),
+                            Ok(None) => info("already installed."),
+                            Err(e) => error(&format!("failed: {}", e)),
+                        }
+                        *outcomes[idx].lock().unwrap_or_else(|e| e.into_inner()) = Some(outcome);
+                    }
+                    set_log_prefix("");
+                }
+            });
+        }
+    });
+
+    outcomes
+        .into_iter()
+        .map(|o| {
+            o.into_inner()
+                .unwrap_or_else(|e| e.into_inner())
+                .unwrap_or_else(|| Err("installation did not run".into()))
+        })
+        .collect()
+}
+
+/// Runs cracker's commands, each method is the counterpart of a subcommand of the command line.
+/// The command line uses [`Cracker::system`], anything else can inject its own filesystem,
+/// executor running the subprocesses (conan, git) and prompt answering questions.
+pub struct Cracker<Fs, E, P> {
+    fs: Fs,
+    executor: E,
+    prompt: P,
+    config: Config,
+    cracker: PathBuf,
+}
+
+impl Cracker<filesystem::OsFileSystem, SystemExecutor, StdinPrompt> {
+    /// The real filesystem and processes, questions are asked on the terminal.
+    pub fn system() -> Self {
+        Self::new(filesystem::OsFileSystem::new(), SystemExecutor, StdinPrompt)
+    }
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P> {
+    /// The storage given on the command line, completed from the config.
+    fn storage(&self, prefix: Option<PathBuf>, bin_dir: Option<PathBuf>) -> Paths {
+        Paths::storage(self.config.prefix(prefix), self.config.bin_dir(bin_dir))
+            .with_cracker(&self.cracker)
+    }
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem + Sync,
+    E: Executor,
+    P: Prompt,
+{
+    pub fn new(fs: Fs, executor: E, prompt: P) -> Self {
+        Self {
+            fs,
+            executor,
+            prompt,
+            config: Config::default(),
+            cracker: PathBuf::from("cracker"),
+        }
+    }
+
+    /// Fills in what the command line and the environment leave out, see [`Config`].
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Executable the shims of binaries provided by many versions call, `cracker` from the `PATH` by default.
+    pub fn with_executable<T: Into<PathBuf>>(mut self, cracker: T) -> Self {
+        self.cracker = cracker.into();
+        self
+    }
+
+    fn policy_prompt<'a, Q: Prompt + ?Sized>(&self, prompt: &'a Q) -> PolicyPrompt<'a, Q> {
+        PolicyPrompt {
+            prompt,
+            conflicts: self.config.conflicts,
+        }
+    }
+
+    fn share_storage(&self, paths: &Paths) -> err::Result<()> {
+        match self.config.permissions {
+            PermissionPolicy::AllUsers => bump_storage_permission(&self.fs, paths),
+            PermissionPolicy::Keep => Ok(()),
+        }
+    }
+
+    /// Installs a conan package and wraps its binaries.
+    pub fn install(&self, i: OptInstall) -> err::Result<InstallReport> {
+        let name = ConanPackage::new(&i.reference)?.name;
+        let i = OptInstall {
+            prefix: self.config.prefix(i.prefix),
+            bin_dir: self.config.bin_dir(i.bin_dir),
+            settings: self.config.settings(&name, i.settings),
+            options: self.config.options(&name, i.options),
+            ..i
+        };
+        let paths = conan_paths(&i)?.with_cracker(&self.cracker);
+        let db = Mutex::new(preinstall(&self.fs, &paths)?);
+
+        let prompt = self.policy_prompt(&self.prompt);
+        let sources = &self.config.recipes;
+        let report =
+            recipe::install_or_build(&self.fs, &self.executor, &prompt, &i, sources, &paths, &db)?;
+        self.share_storage(&paths)?;
+        let db = db.into_inner().unwrap_or_else(|e| e.into_inner());
+        db.save(&self.fs, paths.db_path())?;
+        Ok(report)
+    }
+
+    /// Clones a git repository and wraps the executables found in its search paths.
+    pub fn git(&self, i: OptGit) -> err::Result<InstallReport> {
+        // a relative path would not survive an import elsewhere.
+        let url = if is_local_repo(&i.url) {
+            let path = self
+                .fs
+                .canonicalize(Path::new(&i.url))
+                .map_err(|e| err::ErrorKind::PathNotFound(i.url.clone(), e.to_string()))?;
+            err::path_str(&path)?.to_owned()
+        } else {
+            i.url
+        };
+        let i = OptGit {
+            prefix: self.config.prefix(i.prefix),
+            bin_dir: self.config.bin_dir(i.bin_dir),
+            url,
+            ..i
+        };
+        let paths = git_paths(&i)?.with_cracker(&self.cracker);
+        let db = Mutex::new(preinstall(&self.fs, &paths)?);
+
+        let prompt = self.policy_prompt(&self.prompt);
+        let report = install_git(&self.fs, &self.executor, &prompt, &i, &paths, &db)?;
+        self.share_storage(&paths)?;
+        let db = db.into_inner().unwrap_or_else(|e| e.into_inner());
+        db.save(&self.fs, paths.db_path())?;
+        Ok(report)
+    }
+
+    /// Installs every package of an exported index, see [`OptImport`].
+    /// Fails with [`err::ErrorKind::ImportFailure`] holding the report if any package failed.
+    /// The settings and options of the packages come from the index, the config adds none.
+    pub fn import(&self, i: OptImport) -> err::Result<ImportReport> {
+        let i = OptImport {
+            prefix: self.config.prefix(i.prefix),
+            bin_dir: self.config.bin_dir(i.bin_dir),
+            ..i
+        };
+        let db = CrackerDatabase::load(&self.fs, i.db_path.clone())?;
+
+        let mut report = ImportReport::default();
+        let mut import_jobs = vec![];
+        for wrapped in db.wrapped.iter() {
+            let name = wrapped.data.name();
+            if (!i.only.is_empty() && !i.only.iter().any(|o| o == name))
+                || i.exclude.iter().any(|e| e == name)
+            {
+                info(&format!("filtered out: {}", wrapped.data.describe()));
+                report.filtered_out.push(wrapped.data.describe());
+                continue;
+            }
+
+            match ImportJob::new(&i, wrapped) {
+                Ok(mut job) => {
+                    job.paths = job.paths.with_cracker(&self.cracker);
+                    import_jobs.push(job)
+                }
+                Err(e) => {
+                    error(&format!(
+                        "failed to install {}: {}",
+                        wrapped.data.describe(),
+                        e
+                    ));
+                    report.failed.push(report::ImportFailure {
+                        package: wrapped.data.describe(),
+                        error: (&e).into(),
+                    });
+                }
+            }
+        }
+
+        if let Some(first) = import_jobs.first() {
+            let storage_db = Mutex::new(preinstall(&self.fs, &first.paths)?);
+            let outcomes = run_import_jobs(
+                &self.fs,
+                &self.executor,
+                &self.policy_prompt(&AssumeYes),
+                &import_jobs,
+                &storage_db,
+                i.jobs,
+            );
+            for (job, outcome) in import_jobs.iter().zip(outcomes) {
+                match outcome {
+                    Ok(Some(installed)) => report.installed.push(installed),
+                    Ok(None) => report.skipped.push(job.entry.data.describe()),
+                    Err(e) => report.failed.push(report::ImportFailure {
+                        package: job.entry.data.describe(),
+                        error: (&e).into(),
+                    }),
+                }
+            }
+            self.share_storage(&first.paths)?;
+        }
+
+        let installed: Vec<String> = report
+            .installed
+            .iter()
+            .filter_map(|r| r.entry.as_ref().map(|e| e.data.describe()))
+            .collect();
+        info(&format!(
+            "installed ({}): [{}]",
+            installed.len(),
+            installed.join(", ")
+        ));
+        info(&format!(
+            "skipped ({}): [{}]",
+            report.skipped.len(),
+            report.skipped.join(", ")
+        ));
+        if report.failed.is_empty() {
+            Ok(report)
+        } else {
+            let failed: Vec<&str> = report.failed.iter().map(|f| f.package.as_str()).collect();
+            error(&format!(
+                "failed ({}): [{}]",
+                failed.len(),
+                failed.join(", ")
+            ));
+            Err(err::ErrorKind::ImportFailure(report).into())
+        }
+    }
+
+    /// The snippet adding the bin dir to PATH for given shell.
+    pub fn env(&self, i: OptEnv) -> err::Result<String> {
+        let shell = i
+            .shell
+            .or_else(Shell::detect)
+            .ok_or("Unable to deduce your shell from $SHELL, pass it with --shell.")?;
+        let paths = self.storage(i.prefix, i.bin_dir);
+
+        Ok(shell.enable_script(&paths.bin_dir()))
+    }
+}
+
+#[cfg(test)]
+mod package_tests {
+    use crate::conan_package::ConanPackage;
+    use crate::err;
+    use crate::report::Resolution;
+    use crate::{
+        already_installed, bump_storage_permission, crack, crackem, detect_build_cmd,
+        expand_mode_to_all_users, extract_git_repo_name, extract_path, generate_enable_script,
+        init_cache, is_local_repo, refresh_wrapper, AssumeYes, CloneDepth, Conan,
+        ConanStorageGuard, CrackRequest, Cracker, CrackerDatabase, CrackerDatabaseData,
+        CrackerDatabaseEntry, CrackerFileSystem, InstallReport, InstallationType, LinkMode,
+        OptDiscovery, OptDoctor, OptEnv, OptGit, OptInstall, Paths, Prompt, ReaderPrompt, Shell,
+        SkipReason, Skipped, Wrapper,
+    };
+    use crate::{executor::output, executor::ScriptedExecutor, shim_script, wrapper_script};
+    use filesystem::{FileSystem, TempDir, TempFileSystem};
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    fn assert_command_generate_output(
+        c: Command,
+        sender: std::sync::mpsc::Sender<String>,
+        stdout: &str,
+    ) -> std::io::Result<std::process::Output> {
+        let invocation = format!("{:?}", c);
+        sender.send(invocation).unwrap();
+
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::{ExitStatus, Output};
+        Ok(Output {
+            status: ExitStatus::from_raw(0i32),
+            stderr: Vec::new(),
+            stdout: stdout.as_bytes().to_vec(),
+        })
+    }
+
+    #[test]
+    fn init_cache_dir_test() {
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+
+        let fs = filesystem::MockFileSystem::new();
+        fs.is_file.return_value(false);
+        let db = init_cache(&fs, &paths).unwrap();
+        assert_eq!(
+            fs.create_dir_all.calls(),
+            vec![
+                PathBuf::from("some/random/path/.cracker_storage"),
+                PathBuf::from("some/random/path/bin"),
+            ]
+        );
+
+        assert!(!db.storage_owned_by.is_empty())
+    }
+
+    #[test]
+    fn init_cache_dir_db_already_exists() {
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+
+        let fs = filesystem::MockFileSystem::new();
+        fs.is_file.return_value(true);
+
+        let username = whoami::username();
+
+        fs.read_file.return_value(Ok(format!(
+            r#"{{"wrapped":[],"storage_owned_by":"{}"}}"#,
+            username
+        )
+        .as_bytes()
+        .to_vec()));
+        let db = init_cache(&fs, &paths).unwrap();
+        assert_eq!(
+            fs.create_dir_all.calls(),
+            vec![PathBuf::from("some/random/path/bin"),]
+        );
+
+        assert!(!db.storage_owned_by.is_empty());
+    }
+
+    #[test]
+    fn init_cache_dir_db_already_exists_diff_username() {
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+
+        let fs = filesystem::MockFileSystem::new();
+        fs.is_file.return_value(true);
+
+        fs.read_file.return_value(Ok(String::from(
+            r#"{"wrapped":[],"storage_owned_by":"not_me"}"#,
+        )
+        .as_bytes()
+        .to_vec()));
+        let result = init_cache(&fs, &paths);
+        let display = format!("{}", result.err().unwrap());
+        assert_eq!(
+            display,
+            format!(
+                "Cracker storage owned by: 'not_me' while you are: '{}'",
+                whoami::username()
+            )
+        );
+        assert!(fs.create_dir_all.calls().is_empty());
+    }
+
+    #[test]
+    fn crack_tests() {
+        let req = CrackRequest {
+            bin: PathBuf::from("binary"),
+            data: CrackerDatabaseData::Conan {
+                conan_pkg: ConanPackage::new("abc/321@a/b").unwrap(),
+                conan_settings: vec![],
+                conan_options: vec![],
+                recipe: None,
+                search_depth: 1,
+                exclude: vec![],
+            },
+            link_mode: LinkMode::Script,
+        };
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+
+        let fs = filesystem::MockFileSystem::new();
+        let mut report = InstallReport::default();
+
+        let mut db = CrackerDatabase {
+            wrapped: vec![],
+            storage_owned_by: String::new(),
+        };
+        assert!(db
+            .wrapped(req.bin.file_name().unwrap().to_str().unwrap())
+            .is_none());
+        crack(
+            &ReaderPrompt::new("".as_bytes()),
+            &fs,
+            &req,
+            &paths,
+            &mut db,
+            &mut report,
+        )
+        .unwrap();
+        assert_eq!(
+            db.wrapped(req.bin.file_name().unwrap().to_str().unwrap()),
+            Some(Wrapper {
+                wrapped_bin: String::from("binary"),
+                target: Some(PathBuf::from("binary")),
+                shadowed: false,
+                link_mode: LinkMode::Script,
+            })
+        );
+        let f = &fs.write_file.calls()[0];
+        assert_eq!(f.0, PathBuf::from("some/random/path/bin/binary"));
+        assert_eq!(
+            std::str::from_utf8(&f.1).unwrap(),
+            r#"#!/bin/bash
+source some/random/path/.cracker_storage/conan_abc/activate_run.sh
+source some/random/path/.cracker_storage/conan_abc/activate.sh
+binary "${@}""#
+        );
+
+        let fs = filesystem::MockFileSystem::new();
+        crack(
+            &ReaderPrompt::new("y".as_bytes()),
+            &fs,
+            &req,
+            &paths,
+            &mut db,
+            &mut report,
+        )
+        .unwrap();
+        assert_eq!(
+            fs.remove_file.calls()[0],
+            PathBuf::from("some/random/path/bin/binary")
+        );
+        let f = &fs.write_file.calls()[0];
+        assert_eq!(f.0, PathBuf::from("some/random/path/bin/binary"));
+        assert_eq!(
+            std::str::from_utf8(&f.1).unwrap(),
+            r#"#!/bin/bash
+source some/random/path/.cracker_storage/conan_abc/activate_run.sh
+source some/random/path/.cracker_storage/conan_abc/activate.sh
+binary "${@}""#
+        );
+
+        // binary wrapped already - user does not want to override.
+        let fs = filesystem::MockFileSystem::new();
+        crack(
+            &ReaderPrompt::new("n".as_bytes()),
+            &fs,
+            &req,
+            &paths,
+            &mut db,
+            &mut report,
+        )
+        .unwrap();
+        assert!(fs.remove_file.calls().is_empty());
+        assert!(fs.write_file.calls().is_empty());
+
+        // another version of the package, kept side by side - the wrapper turns into a shim.
+        let other_req = CrackRequest {
+            bin: PathBuf::from("other/binary"),
+            data: CrackerDatabaseData::Conan {
+                conan_pkg: ConanPackage::new("abc/123@a/b").unwrap(),
+                conan_settings: vec![],
+                conan_options: vec![],
+                recipe: None,
+                search_depth: 1,
+                exclude: vec![],
+            },
+            link_mode: LinkMode::Script,
+        };
+        let other_paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc_123".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+        let fs = filesystem::MockFileSystem::new();
+        crack(
+            &ReaderPrompt::new("n".as_bytes()),
+            &fs,
+            &other_req,
+            &other_paths,
+            &mut db,
+            &mut report,
+        )
+        .unwrap();
+        assert_eq!(
+            db.wrapped("binary").unwrap().target,
+            Some(PathBuf::from("binary"))
+        );
+        assert_eq!(db.providers("binary").len(), 2);
+        assert!(db.providers("binary")[1].1.shadowed);
+        let f = &fs.write_file.calls()[0];
+        assert_eq!(f.0, PathBuf::from("some/random/path/bin/binary"));
+        let shim = std::str::from_utf8(&f.1).unwrap();
+        assert!(shim.starts_with("#!/bin/bash\nexec "));
+        assert!(shim.ends_with(
+            r#" exec --prefix some/random/path --bin-dir some/random/path/bin binary -- "${@}""#
+        ));
+        assert!(crate::WrapperScript::parse(shim).unwrap().shim);
+
+        // switching the default back and forth keeps the shim.
+        let fs = filesystem::MockFileSystem::new();
+        crack(
+            &ReaderPrompt::new("y".as_bytes()),
+            &fs,
+            &other_req,
+            &other_paths,
+            &mut db,
+            &mut report,
+        )
+        .unwrap();
+        assert_eq!(
+            db.wrapped("binary").unwrap().target,
+            Some(PathBuf::from("other/binary"))
+        );
+        assert!(db.providers("binary")[0].1.shadowed);
+
+        assert_eq!(report.wrappers_created.len(), 4);
+        assert_eq!(report.wrappers_skipped, vec![String::from("binary")]);
+        assert_eq!(
+            report
+                .conflicts
+                .iter()
+                .map(|c| (c.provided_by.as_str(), c.resolution))
+                .collect::<Vec<_>>(),
+            vec![
+                ("abc/321@a/b", Resolution::Overwritten),
+                ("abc/321@a/b", Resolution::Kept),
+                ("abc/321@a/b", Resolution::KeptDefault),
+                ("abc/321@a/b", Resolution::MadeDefault),
+            ]
+        );
+    }
+
+    #[test]
+    fn already_installed_test() {
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+        let data = CrackerDatabaseData::Conan {
+            conan_pkg: ConanPackage::new("abc/321@a/b").unwrap(),
+            conan_settings: vec![],
+            conan_options: vec![],
+            recipe: None,
+            search_depth: 1,
+            exclude: vec![],
+        };
+        let wrapper = |name: &str| Wrapper {
+            wrapped_bin: name.to_owned(),
+            target: Some(PathBuf::from(name)),
+            shadowed: false,
+            link_mode: LinkMode::Script,
+        };
+        let db = CrackerDatabase {
+            wrapped: vec![CrackerDatabaseEntry {
+                data: data.clone(),
+                wrappers: vec![wrapper("abc"), wrapper("cba")],
+                install_folder: String::from("some/random/path/.cracker_storage/conan_abc"),
+            }],
+            storage_owned_by: String::new(),
+        };
+        let imported = CrackerDatabaseEntry {
+            data: data.clone(),
+            wrappers: vec![wrapper("abc")],
+            install_folder: String::from("other/prefix/.cracker_storage/conan_abc"),
+        };
+
+        let fs = filesystem::MockFileSystem::new();
+        fs.is_dir.return_value(true);
+        fs.is_file.return_value(true);
+        assert!(already_installed(&fs, &paths, &db, &imported));
+
+        let mut missing_wrapper = imported.clone();
+        missing_wrapper.wrappers.push(wrapper("xyz"));
+        assert!(!already_installed(&fs, &paths, &db, &missing_wrapper));
+
+        let mut other_settings = imported.clone();
+        other_settings.data = CrackerDatabaseData::Conan {
+            conan_pkg: ConanPackage::new("abc/321@a/b").unwrap(),
+            conan_settings: vec![String::from("build_type=Debug")],
+            conan_options: vec![],
+            recipe: None,
+            search_depth: 1,
+            exclude: vec![],
+        };
+        assert!(!already_installed(&fs, &paths, &db, &other_settings));
+
+        let fs = filesystem::MockFileSystem::new();
+        fs.is_dir.return_value(true);
+        fs.is_file.return_value(false);
+        assert!(!already_installed(&fs, &paths, &db, &imported));
+
+        let fs = filesystem::MockFileSystem::new();
+        fs.is_dir.return_value(false);
+        fs.is_file.return_value(true);
+        assert!(!already_installed(&fs, &paths, &db, &imported));
+    }
+
+    /// the storage switch is process wide, tests touching it cannot run concurrently.
+    static STORAGE_SWITCH_TEST: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn conan_install_fun() {
+        let _lock = STORAGE_SWITCH_TEST
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let expected_invocations = vec![
+            String::from(r#""conan" "config" "get" "storage.path""#),
+            String::from(
+                r#""conan" "config" "set" "storage.path=some/random/path/.cracker_storage/.conan""#,
+            ),
+            String::from(
+                r#""conan" "install" "abc/321@" "-if" "some_folder" "-g" "virtualrunenv" "-g" "virtualenv" "-s" "some_set" "-s" "another_one" "-o" "opt""#,
+            ),
+            String::from(r#""conan" "config" "set" "storage.path=abc""#),
+        ];
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+
+        let fs = filesystem::MockFileSystem::new();
+        Conan::new(&fs, &|c| {
+            assert_command_generate_output(c, sender.clone(), "abc")
+        })
+        .unwrap()
+        .install(
+            &ConanPackage::new("abc/321@").unwrap(),
+            &paths,
+            "some_folder",
+            &[String::from("some_set"), String::from("another_one")],
+            &[String::from("opt")],
+        )
+        .unwrap();
+        let captured_invocations: Vec<String> = receiver.try_iter().collect();
+        assert_eq!(captured_invocations, expected_invocations);
+    }
+
+    #[test]
+    fn storage_guard_shared_between_installs() {
+        let _lock = STORAGE_SWITCH_TEST
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let executor = |c| assert_command_generate_output(c, sender.clone(), "original\n");
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+
+        let fs = filesystem::FakeFileSystem::new();
+        fs.create_dir_all(paths.storage_dir()).unwrap();
+
+        let first = ConanStorageGuard::new(&fs, &executor, &paths).unwrap();
+        let second = ConanStorageGuard::new(&fs, &executor, &paths).unwrap();
+        drop(first);
+        assert_eq!(
+            fs.read_file_to_string(paths.storage_path_backup()).unwrap(),
+            "original"
+        );
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<String>>(),
+            vec![
+                String::from(r#""conan" "config" "get" "storage.path""#),
+                String::from(
+                    r#""conan" "config" "set" "storage.path=some/random/path/.cracker_storage/.conan""#
+                ),
+            ]
+        );
+
+        drop(second);
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<String>>(),
+            vec![String::from(
+                r#""conan" "config" "set" "storage.path=original""#
+            )]
+        );
+        assert!(!fs.is_file(paths.storage_path_backup()));
+    }
+
+    #[test]
+    fn storage_guard_errors() {
+        let _lock = STORAGE_SWITCH_TEST
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let paths = Paths::new(
+            Some(PathBuf::from("/prefix")),
+            None,
+            InstallationType::Conan,
+            "abc",
+        );
+        let fs = filesystem::FakeFileSystem::new();
+        fs.create_dir_all(paths.storage_dir()).unwrap();
+
+        let no_conan = ScriptedExecutor::new();
+        match ConanStorageGuard::new(&fs, &no_conan, &paths) {
+            Err(e) => assert_eq!(e.kind().exit_code(), 4, "{}", e),
+            Ok(_) => panic!("guard without conan"),
+        }
+
+        let read_only = ScriptedExecutor::new()
+            .expect("conan config get storage.path", "original\n")
+            .expect_failure(
+                "conan config set storage.path=/prefix/.cracker_storage/.conan",
+                1,
+                "permission denied",
+            );
+        match ConanStorageGuard::new(&fs, &read_only, &paths) {
+            Err(e) => match e.0 {
+                err::ErrorKind::ConanStorageSwitchFailure(reason) => {
+                    assert!(reason.contains("permission denied"), "{}", reason)
+                }
+                e => panic!("unexpected error: {}", e),
+            },
+            Ok(_) => panic!("guard with failing conan config set"),
+        }
+        read_only.verify();
+        assert!(!fs.is_file(paths.storage_path_backup()));
+    }
+
+    #[test]
+    fn permissions() {
+        let paths = Paths {
+            prefix: PathBuf::from("some/random/path"),
+            bin_dir: PathBuf::from("some/random/path/bin"),
+            install_type: InstallationType::Conan,
+            pkg_name: "abc".to_owned(),
+            cracker: PathBuf::from("cracker"),
+        };
+        let fs = filesystem::MockFileSystem::new();
+        generate_enable_script(&fs, &paths).unwrap();
+
+        let calls = fs.write_file.calls();
+        assert_eq!(
+            calls.iter().map(|c| c.0.clone()).collect::<Vec<PathBuf>>(),
+            vec![
+                PathBuf::from("some/random/path/cracker_enable"),
+                PathBuf::from("some/random/path/cracker_enable.zsh"),
+                PathBuf::from("some/random/path/cracker_enable.fish"),
+                PathBuf::from("some/random/path/cracker_enable.tcsh"),
+            ]
+        );
+
+        assert_eq!(
+            std::str::from_utf8(&calls[0].1).unwrap(),
+            r#"case ":${PATH}:" in
+    *:"some/random/path/bin":*) ;;
+    *) export PATH="some/random/path/bin:${PATH}" ;;
+esac"#
+        )
+        // let f = std::fs::Permissions::
+    }
+    #[test]
+    fn extract_test_path() {
+        let fs = filesystem::MockFileSystem::new();
+        fs.read_file.return_value(Ok(String::from(
+            r#"
+abcabcabc
+PATH="wole":"abc"
+        "#,
+        )
+        .into_bytes()));
+
+        assert_eq!(
+            extract_path(&fs, PathBuf::new()).unwrap(),
+            String::from("wole")
+        );
+
+        fs.read_file
+            .return_value(Ok(String::from("ABC=\"def\"\n").into_bytes()));
+        match extract_path(&fs, PathBuf::from("env")).err().unwrap().0 {
+            err::ErrorKind::ConanEnvWithoutPath(file) => assert_eq!(file, PathBuf::from("env")),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn expand_mode_to_all_users_test() {
+        assert_eq!(expand_mode_to_all_users(0o100u32), 0o111);
+        assert_eq!(expand_mode_to_all_users(0o300u32), 0o333);
+        assert_eq!(expand_mode_to_all_users(0o644u32), 0o666);
+        assert_eq!(expand_mode_to_all_users(0o713u32), 0o777);
+        assert_eq!(expand_mode_to_all_users(0o134u32), 0o135);
+    }
+
+    #[test]
+    fn extract_git_url_project_name() {
+        assert_eq!(
+            extract_git_repo_name("https://github.com/fulara/conan-cracker.git").unwrap(),
+            String::from("conan-cracker")
+        );
+        for url in &[
+            "https://github.com/fulara/conan-cracker",
+            "https://github.com/fulara/conan-cracker/",
+            "ssh://git@github.com:22/fulara/conan-cracker.git",
+            "git@github.com:fulara/conan-cracker.git",
+            "github.com:conan-cracker",
+            "file:///srv/repos/conan-cracker",
+            "/srv/repos/conan-cracker.git",
+            "../conan-cracker/.git",
+            "conan-cracker",
+        ] {
+            assert_eq!(
+                extract_git_repo_name(url).unwrap(),
+                "conan-cracker",
+                "{}",
+                url
+            );
+        }
+        for url in &[
+            "https://github.com",
+            "https://github.com/",
+            "git@github.com:",
+            ".",
+        ] {
+            let e = extract_git_repo_name(url).unwrap_err();
+            assert_eq!(e.kind().exit_code(), 9, "{}", url);
+        }
+        assert!(is_local_repo("./a:b"));
+        assert!(!is_local_repo("git@host:a"));
+    }
+
+    fn write_executable(path: &std::path::Path, content: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn links_turn_into_shims_and_back() {
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = fs.temp_dir("cracker").unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let mut db = CrackerDatabase::new();
+        let install = |pkg_name: &str, link_mode, db: &mut CrackerDatabase| {
+            let paths = Paths::new(
+                Some(prefix.clone()),
+                None,
+                InstallationType::Local,
+                pkg_name,
+            );
+            let bin = paths.install_folder().join("tool");
+            fs.create_dir_all(paths.bin_dir()).unwrap();
+            write_executable(&bin, "#!/bin/sh\necho tool");
+            let request = CrackRequest {
+                bin,
+                data: CrackerDatabaseData::Local {
+                    pkg_name: String::from("tool"),
+                    path: paths.install_folder(),
+                    copied: true,
+                    search_paths: vec![],
+                    search_depth: 1,
+                    exclude: vec![],
+                },
+                link_mode,
+            };
+            let mut report = InstallReport::default();
+            crack(&AssumeYes, &fs, &request, &paths, db, &mut report).unwrap();
+            paths
+        };
+
+        let paths = install("tool_1", LinkMode::Symlink, &mut db);
+        let wrapper = paths.bin_dir().join("tool");
+        let target = paths.install_folder().join("tool");
+        assert_eq!(fs.read_link(&wrapper).unwrap(), target);
+
+        // a shim replaces the link, the binary it pointed at stays as it was.
+        let other = install("tool_2", LinkMode::Hardlink, &mut db);
+        assert!(!fs.is_symlink(&wrapper));
+        assert!(crate::WrapperScript::read(&fs, &wrapper).unwrap().shim);
+        assert_eq!(
+            fs.read_file_to_string(&target).unwrap(),
+            "#!/bin/sh\necho tool"
+        );
+
+        db.unregister_pkg(other.install_folder().to_str().unwrap());
+        db.set_default("tool", None);
+        refresh_wrapper(&fs, &paths, &db, "tool").unwrap();
+        assert_eq!(fs.read_link(&wrapper).unwrap(), target);
+
+        db.unregister_pkg(paths.install_folder().to_str().unwrap());
+        refresh_wrapper(&fs, &paths, &db, "tool").unwrap();
+        assert!(!fs.is_symlink(&wrapper));
+        assert!(fs.is_file(&target));
+    }
+
+    #[test]
+    fn failing_links_are_typed_errors() {
+        // in memory every link fails, as a hardlink would on a filesystem without them.
+        let fs = filesystem::FakeFileSystem::new();
+        let paths = Paths::new(
+            Some(PathBuf::from("/prefix")),
+            None,
+            InstallationType::Local,
+            "tool",
+        );
+        let bin = paths.install_folder().join("tool");
+        fs.create_dir_all(paths.bin_dir()).unwrap();
+        fs.create_dir_all(paths.install_folder()).unwrap();
+        fs.create_file(&bin, "#!/bin/sh\necho tool").unwrap();
+        let mut db = CrackerDatabase::new();
+        db.register_wrap(
+            Wrapper {
+                wrapped_bin: String::from("tool"),
+                target: Some(bin.clone()),
+                shadowed: false,
+                link_mode: LinkMode::Hardlink,
+            },
+            paths.install_folder().to_str().unwrap(),
+            CrackerDatabaseData::Local {
+                pkg_name: String::from("tool"),
+                path: paths.install_folder(),
+                copied: true,
+                search_paths: vec![],
+                search_depth: 1,
+                exclude: vec![],
+            },
+        );
+
+        let e = refresh_wrapper(&fs, &paths, &db, "tool").unwrap_err();
+        match e.kind() {
+            err::ErrorKind::WrapperLinkFailure(wrapper, target, _) => {
+                assert_eq!(*wrapper, paths.bin_dir().join("tool"));
+                assert_eq!(*target, bin);
+            }
+            kind => panic!("not a link failure: {:?}", kind),
+        }
+        assert_eq!(e.kind().exit_code(), 30);
+    }
+
+    #[test]
+    fn shims_call_the_configured_executable() {
+        let paths = Paths::new(
+            Some(PathBuf::from("/prefix")),
+            Some(PathBuf::from("/bin_dir")),
+            InstallationType::Local,
+            "tool",
+        );
+        assert_eq!(
+            shim_script(&paths, "tool"),
+            "#!/bin/bash\nexec cracker exec --prefix /prefix --bin-dir /bin_dir tool -- \"${@}\""
+        );
+
+        let paths = paths.with_cracker(Path::new("/opt/cracker/bin/cracker"));
+        assert_eq!(
+            shim_script(&paths, "tool"),
+            "#!/bin/bash\nexec /opt/cracker/bin/cracker exec --prefix /prefix --bin-dir /bin_dir tool -- \"${@}\""
+        );
+    }
+
+    #[test]
+    fn crackem_in_memory() {
+        let fs = filesystem::FakeFileSystem::new();
+        let paths = Paths::new(
+            Some(PathBuf::from("/prefix")),
+            None,
+            InstallationType::Git,
+            "tools",
+        );
+        let root = paths.install_folder().join("scripts");
+        fs.create_dir_all(root.join("nested")).unwrap();
+        fs.create_dir_all(paths.bin_dir()).unwrap();
+        for (name, mode) in [
+            ("tool.sh", 0o700),
+            ("README", 0o600),
+            ("nested/deep.sh", 0o755),
+            ("libtool.so", 0o755),
+        ] {
+            let content = if name.ends_with(".sh") {
+                "#!/bin/sh"
+            } else {
+                ""
+            };
+            fs.create_file(root.join(name), content).unwrap();
+            fs.set_mode(&root.join(name), mode).unwrap();
+        }
+
+        let mut db = CrackerDatabase::new();
+        let report = crackem(
+            &fs,
+            &AssumeYes,
+            &paths,
+            &mut db,
+            root.to_str().unwrap().to_owned(),
+            &OptDiscovery::default(),
+            CrackerDatabaseData::Git {
+                pkg_name: String::from("tools"),
+                url: String::from("https://example.com/tools.git"),
+                label: String::new(),
+                search_paths: vec![],
+                build_cmd: None,
+                recursive: false,
+                depth: CloneDepth::default(),
+                sparse: vec![],
+                search_depth: 1,
+                exclude: vec![],
+            },
+            LinkMode::Script,
+        )
+        .unwrap();
+
+        assert_eq!(
+            db.providers("tool.sh")[0].1.target,
+            Some(root.join("tool.sh"))
+        );
+        assert!(db.providers("README").is_empty());
+        assert!(db.providers("deep.sh").is_empty());
+        assert!(db.providers("libtool.so").is_empty());
+        assert_eq!(report.wrappers_created, vec![String::from("tool.sh")]);
+        assert_eq!(
+            report.files_skipped,
+            vec![Skipped {
+                path: root.join("libtool.so"),
+                reason: SkipReason::NotRunnable,
+            }]
+        );
+        let wrapper = paths.bin_dir().join("tool.sh");
+        assert!(fs
+            .read_file_to_string(&wrapper)
+            .unwrap()
+            .contains("tool.sh"));
+        assert_eq!(fs.mode(&wrapper).unwrap() & 0o111, 0o111);
+
+        db.save(&fs, paths.db_path()).unwrap();
+        assert_eq!(
+            CrackerDatabase::load(&fs, paths.db_path()).unwrap().wrapped,
+            db.wrapped
+        );
+
+        bump_storage_permission(&fs, &paths).unwrap();
+        assert_eq!(fs.mode(&root.join("tool.sh")).unwrap(), 0o777);
+        assert_eq!(fs.mode(&root.join("README")).unwrap(), 0o666);
+    }
+
+    #[test]
+    fn install_end_to_end() {
+        let _lock = STORAGE_SWITCH_TEST
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = fs.temp_dir("cracker").unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let storage = prefix.join(".cracker_storage");
+        let if_path = storage.join("conan_abc_321");
+        let pkg_bin = storage.join(".conan/abc/321/_/_/package/1234/bin");
+
+        let executor = ScriptedExecutor::new()
+            .expect("conan config get storage.path", "original\n")
+            .expect(
+                &format!(
+                    "conan config set storage.path={}",
+                    storage.join(".conan").display()
+                ),
+                "",
+            )
+            .expect_with(
+                &format!(
+                    "conan install abc/321@ -if {} -g virtualrunenv -g virtualenv -s os=Linux",
+                    if_path.display()
+                ),
+                {
+                    let (if_path, pkg_bin) = (if_path.clone(), pkg_bin.clone());
+                    move |_| {
+                        std::fs::create_dir_all(&if_path)?;
+                        std::fs::write(
+                            if_path.join("environment_run.sh.env"),
+                            format!("PATH=\"{}\"${{PATH+:$PATH}}", pkg_bin.display()),
+                        )?;
+                        write_executable(&pkg_bin.join("abc"), "#!/bin/sh");
+                        std::fs::write(pkg_bin.join("abc.txt"), "not a binary")?;
+                        Ok(output(0, "", ""))
+                    }
+                },
+            )
+            .expect("conan config set storage.path=original", "");
+        let cracker = Cracker::new(fs, executor, AssumeYes);
+
+        cracker
+            .install(OptInstall {
+                prefix: Some(prefix.clone()),
+                bin_dir: None,
+                reference: String::from("abc/321@"),
+                discovery: Default::default(),
+                settings: vec![String::from("os=Linux")],
+                options: vec![],
+                no_build: false,
+                log: false,
+            })
+            .unwrap();
+        cracker.executor.verify();
+
+        let target = std::fs::canonicalize(pkg_bin.join("abc")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(prefix.join("bin/abc")).unwrap(),
+            wrapper_script(if_path.to_str().unwrap(), &target, true)
+        );
+        assert!(!prefix.join("bin/abc.txt").exists());
+        assert!(!storage.join(".conan_original_storage_path").exists());
+
+        let db = CrackerDatabase::load(&cracker.fs, prefix.join(".cracker_index")).unwrap();
+        assert_eq!(
+            db.wrapped,
+            vec![CrackerDatabaseEntry {
+                data: CrackerDatabaseData::Conan {
+                    conan_pkg: ConanPackage::new("abc/321@").unwrap(),
+                    conan_settings: vec![String::from("os=Linux")],
+                    conan_options: vec![],
+                    recipe: None,
+                    search_depth: 1,
+                    exclude: vec![],
+                },
+                wrappers: vec![Wrapper {
+                    wrapped_bin: String::from("abc"),
+                    target: Some(target),
+                    shadowed: false,
+                    link_mode: LinkMode::Script,
+                }],
+                install_folder: if_path.to_str().unwrap().to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn git_install_end_to_end() {
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = fs.temp_dir("cracker").unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let if_path = prefix.join(".cracker_storage/git_tools");
+        let opt = || OptGit {
+            prefix: Some(prefix.clone()),
+            bin_dir: None,
+            url: String::from("https://example.com/tools.git"),
+            name: None,
+            discovery: Default::default(),
+            search_paths: vec![String::from("scripts")],
+            build_cmd: None,
+            recursive: false,
+            depth: CloneDepth::default(),
+            sparse: vec![],
+            link_mode: LinkMode::Script,
+            log: false,
+        };
+        let clone = format!(
+            "git clone https://example.com/tools.git --depth 1 {}",
+            if_path.display()
+        );
+
+        let cracker = Cracker::new(
+            fs,
+            ScriptedExecutor::new().expect_failure(&clone, 128, "fatal: repository not found"),
+            AssumeYes,
+        );
+        let e = cracker.git(opt()).err().unwrap();
+        assert!(e.to_string().contains("fatal: repository not found"));
+
+        let cracker = Cracker::new(
+            filesystem::OsFileSystem::new(),
+            ScriptedExecutor::new().expect_with(&clone, {
+                let if_path = if_path.clone();
+                move |_| {
+                    write_executable(&if_path.join("scripts/tool.sh"), "#!/bin/sh");
+                    write_executable(&if_path.join("ignored.sh"), "#!/bin/sh");
+                    Ok(output(0, "", ""))
+                }
+            }),
+            AssumeYes,
+        );
+        cracker.git(opt()).unwrap();
+        cracker.executor.verify();
+
+        let target = std::fs::canonicalize(if_path.join("scripts/tool.sh")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(prefix.join("bin/tool.sh")).unwrap(),
+            wrapper_script(if_path.to_str().unwrap(), &target, false)
+        );
+        assert!(!prefix.join("bin/ignored.sh").exists());
+        let db = CrackerDatabase::load(&cracker.fs, prefix.join(".cracker_index")).unwrap();
+        assert_eq!(db.wrapped[0].data.name(), "tools");
+        assert_eq!(db.wrapped[0].wrappers[0].wrapped_bin, "tool.sh");
+    }
+
+    #[test]
+    fn git_clone_options() {
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = fs.temp_dir("cracker").unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let if_path = prefix.join(".cracker_storage/git_tools");
+        let opt = OptGit {
+            prefix: Some(prefix.clone()),
+            bin_dir: None,
+            url: String::from("https://example.com/tools.git"),
+            name: None,
+            discovery: Default::default(),
+            search_paths: vec![String::from("tools/bin")],
+            build_cmd: None,
+            recursive: true,
+            depth: "full".parse().unwrap(),
+            sparse: vec![String::from("tools")],
+            link_mode: LinkMode::Script,
+            log: false,
+        };
+        let clone = format!(
+            "git clone https://example.com/tools.git --sparse {}",
+            if_path.display()
+        );
+
+        let cracker = Cracker::new(
+            fs,
+            ScriptedExecutor::new()
+                .expect_with(&clone, {
+                    let if_path = if_path.clone();
+                    move |_| {
+                        std::fs::create_dir_all(&if_path)?;
+                        Ok(output(0, "", ""))
+                    }
+                })
+                .expect("git sparse-checkout set tools", "")
+                .expect_with("git submodule update --init --recursive", {
+                    let if_path = if_path.clone();
+                    move |c| {
+                        assert_eq!(c.get_current_dir(), Some(if_path.as_path()));
+                        write_executable(&if_path.join("tools/bin/tool"), "#!/bin/sh");
+                        Ok(output(0, "", ""))
+                    }
+                }),
+            AssumeYes,
+        );
+        let report = cracker.git(opt).unwrap();
+        cracker.executor.verify();
+        assert_eq!(report.wrappers_created, vec![String::from("tool")]);
+        match report.entry.unwrap().data {
+            CrackerDatabaseData::Git {
+                recursive,
+                depth,
+                sparse,
+                ..
+            } => {
+                assert!(recursive);
+                assert_eq!(depth, CloneDepth::Full);
+                assert_eq!(sparse, vec![String::from("tools")]);
+            }
+            data => panic!("not a git package: {:?}", data),
+        }
+
+        // indices of older versions cloned a single commit without submodules and wrapped what
+        // was directly in the search paths.
+        let data: CrackerDatabaseData = serde_json::from_str(
+            r#"{"Git": {"pkg_name": "a", "url": "a.git", "label": "", "search_paths": []}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            data.discovery(vec![String::from("a")]),
+            OptDiscovery {
+                wrappers: vec![String::from("a")],
+                ..OptDiscovery::default()
+            }
+        );
+        match data {
+            CrackerDatabaseData::Git {
+                recursive, depth, ..
+            } => {
+                assert!(!recursive);
+                assert_eq!(depth, CloneDepth::Commits(1));
+            }
+            data => panic!("not a git package: {:?}", data),
+        }
+        assert_eq!("20".parse(), Ok(CloneDepth::Commits(20)));
+        assert!("0".parse::<CloneDepth>().is_err());
+        assert!("shallow".parse::<CloneDepth>().is_err());
+    }
+
+    #[test]
+    fn git_build_end_to_end() {
+        let fs = filesystem::OsFileSystem::new();
+        let tmp = fs.temp_dir("cracker").unwrap();
+        let prefix = tmp.path().to_path_buf();
+        let if_path = prefix.join(".cracker_storage/git_tools");
+        let opt = || OptGit {
+            prefix: Some(prefix.clone()),
+            bin_dir: None,
+            url: String::from("https://example.com/tools.git"),
+            name: None,
+            discovery: Default::default(),
+            search_paths: vec![String::from("build")],
+            build_cmd: Some(String::from("auto")),
+            recursive: false,
+            depth: CloneDepth::default(),
+            sparse: vec![],
+            link_mode: LinkMode::Script,
+            log: false,
+        };
+        let clone = format!(
+            "git clone https://example.com/tools.git --depth 1 {}",
+            if_path.display()
+        );
+        let cloned = {
+            let if_path = if_path.clone();
+            move |_: &Command| {
+                std::fs::create_dir_all(&if_path)?;
+                std::fs::write(if_path.join("Makefile"), "")?;
+                Ok(output(0, "", ""))
+            }
+        };
+
+        let cracker = Cracker::new(
+            fs,
+            ScriptedExecutor::new()
+                .expect_with(&clone, cloned.clone())
+                .expect_failure("sh -c make", 2, "make: *** [all] Error 1"),
+            AssumeYes,
+        );
+        let e = cracker.git(opt()).err().unwrap();
+        assert_eq!(e.kind().exit_code(), 17);
+        assert!(!if_path.exists());
+
+        let cracker = Cracker::new(
+            filesystem::OsFileSystem::new(),
+            ScriptedExecutor::new()
+                .expect_with(&clone, cloned)
+                .expect_with("sh -c make", {
+                    let if_path = if_path.clone();
+                    move |c| {
+                        assert_eq!(c.get_current_dir(), Some(if_path.as_path()));
+                        write_executable(&if_path.join("build/tool"), "#!/bin/sh");
+                        Ok(output(0, "", ""))
+                    }
+                }),
+            AssumeYes,
+        );
+        let report = cracker.git(opt()).unwrap();
+        cracker.executor.verify();
+
+        assert_eq!(report.wrappers_created, vec![String::from("tool")]);
+        match report.entry.unwrap().data {
+            CrackerDatabaseData::Git { build_cmd, .. } => {
+                assert_eq!(build_cmd, Some(String::from("make")))
+            }
+            data => panic!("not a git package: {:?}", data),
+        }
+
+        let fs = filesystem::FakeFileSystem::new();
+        fs.create_dir_all("/repo").unwrap();
+        assert_eq!(detect_build_cmd(&fs, Path::new("/repo")), None);
+        fs.create_file("/repo/Makefile", "").unwrap();
+        fs.create_file("/repo/Cargo.toml", "").unwrap();
+        assert_eq!(
+            detect_build_cmd(&fs, Path::new("/repo")),
+            Some("cargo build --release")
+        );
+    }
+
+    #[test]
+    fn reader_prompt() {
+        let prompt = ReaderPrompt::new("maybe\nYes\nn\n".as_bytes());
+        assert!(prompt.confirm("first?"));
+        assert!(!prompt.confirm("second?"));
+        // nothing left to read - no.
+        assert!(!prompt.confirm("third?"));
+    }
+
+    #[test]
+    fn cracker_with_injected_dependencies() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sender = std::sync::Mutex::new(sender);
+        let cracker = Cracker::new(
+            filesystem::FakeFileSystem::new(),
+            move |c| {
+                let sender = sender.lock().unwrap().clone();
+                assert_command_generate_output(c, sender, "./data")
+            },
+            AssumeYes,
+        );
+
+        cracker
+            .doctor(OptDoctor {
+                prefix: Some(PathBuf::from("/prefix")),
+                bin_dir: None,
+                fix: false,
+            })
+            .unwrap();
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<String>>(),
+            vec![String::from(r#""conan" "config" "get" "storage.path""#)]
+        );
+
+        assert_eq!(
+            cracker
+                .env(OptEnv {
+                    prefix: Some(PathBuf::from("/prefix")),
+                    bin_dir: None,
+                    shell: Some(Shell::Fish),
+                })
+                .unwrap(),
+            Shell::Fish.enable_script(std::path::Path::new("/prefix/bin"))
+        );
+    }
+
+    #[test]
+    fn constructors_match_the_command_line() {
+        use crate::{OptArchive, OptBuild, OptExec, OptImport, OptLocal, OptWhich};
+        use structopt::StructOpt;
+
+        fn parse_like<T: StructOpt>(_: &T, args: &[&str]) -> T {
+            T::from_iter_safe(args).unwrap()
+        }
+        // the storage is given on both sides, the command line may take it from the environment.
+        macro_rules! assert_parsed {
+            ($opt:expr, $args:expr) => {{
+                let mut opt = $opt;
+                opt.prefix = Some(PathBuf::from("/p"));
+                opt.bin_dir = Some(PathBuf::from("/b"));
+                let (command, rest) = $args.split_first().unwrap();
+                let mut args = vec![*command, "--prefix", "/p", "--bin-dir", "/b"];
+                args.extend(rest);
+                assert_eq!(
+                    format!("{:?}", opt),
+                    format!("{:?}", parse_like(&opt, &args))
+                );
+            }};
+        }
+
+        assert_parsed!(OptInstall::new("abc/1.0@"), ["install", "abc/1.0@"]);
+        assert_parsed!(OptBuild::new("lz4", "1.9"), ["build", "lz4", "1.9"]);
+        assert_parsed!(OptGit::new("a.git"), ["git", "a.git"]);
+        assert_parsed!(OptArchive::new("a.zip"), ["archive", "a.zip"]);
+        assert_parsed!(OptLocal::new("tools"), ["local", "tools"]);
+        assert_parsed!(OptImport::new("index"), ["import", "index"]);
+        assert_parsed!(OptWhich::new("abc"), ["which", "abc"]);
+        assert_parsed!(
+            OptExec::new("abc", vec![String::from("-v")]),
+            ["exec", "abc", "--", "-v"]
+        );
+        let doctor = OptDoctor::default();
+        assert_parsed!(doctor, ["doctor"]);
+    }
+}
@@ -0,0 +1,229 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::log::InstallLog;
+use crate::{
+    check_wrappers, crackem, err, info, lock, make_sure_if_empty, preinstall, warn, Cracker,
+    CrackerDatabase, CrackerFileSystem, Executor, InstallReport, InstallationType, OptLocal, Paths,
+    Prompt,
+};
+
+pub(crate) fn local_paths(i: &OptLocal) -> err::Result<Paths> {
+    let name = match &i.name {
+        Some(name) => name.clone(),
+        None => i
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| err::ErrorKind::UnableToName(i.path.clone()))?,
+    };
+    Ok(Paths::new(
+        i.prefix.clone(),
+        i.bin_dir.clone(),
+        InstallationType::Local,
+        &name,
+    ))
+}
+
+/// Copies `from` into `to` keeping the permissions. Symlinked files are copied as the file they point to,
+/// symlinked directories are left out.
+fn copy_dir<Fs: CrackerFileSystem>(fs: &Fs, from: &Path, to: &Path) -> err::Result<()> {
+    for p in fs.walk(from, None)? {
+        let target = match p.strip_prefix(from) {
+            Ok(relative) => to.join(relative),
+            Err(_) => continue,
+        };
+        if fs.is_symlink(&p) && fs.is_dir(&p) {
+            warn(&format!("Skipped symlinked directory: {}", p.display()));
+        } else if fs.is_dir(&p) {
+            fs.create_dir_all(&target)?;
+        } else {
+            fs.copy_file(&p, &target)?;
+            fs.set_mode(&target, fs.mode(&p)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `i.path` is expected to be canonical. The install folder exists either way, for a package run
+/// in place it stays empty: removing the package must never touch the directory it came from.
+pub(crate) fn install_local<Fs, P>(
+    fs: &Fs,
+    prompt: &P,
+    i: &OptLocal,
+    paths: &Paths,
+    db: &Mutex<CrackerDatabase>,
+) -> err::Result<InstallReport>
+where
+    Fs: CrackerFileSystem,
+    P: Prompt + ?Sized,
+{
+    let _log = InstallLog::start(fs, i.log.then(|| paths.install_log()));
+    let pkg_name = paths.pkg_name.clone();
+    let mut report = InstallReport::default();
+    let path = &i.path;
+    if !fs.is_dir(path) {
+        return Err(err::ErrorKind::NotADirectory(path.clone()).into());
+    }
+    if !make_sure_if_empty(fs, prompt, &pkg_name, paths, &mut lock(db))? {
+        warn("Unable to install package.");
+        return Ok(report);
+    }
+
+    let install_folder = paths.install_folder();
+    let root = if i.copy {
+        info(&format!("Copying {}", path.display()));
+        copy_dir(fs, path, &install_folder)?;
+        install_folder.clone()
+    } else {
+        fs.create_dir_all(&install_folder)?;
+        path.clone()
+    };
+
+    let roots: Vec<PathBuf> = i.search_paths.iter().map(|p| root.join(p)).collect();
+    check_wrappers(fs, &install_folder, &roots, &i.discovery)?;
+    let mut db = lock(db);
+    for search_path in roots {
+        report.extend(crackem(
+            fs,
+            prompt,
+            paths,
+            &mut db,
+            err::path_str(&search_path)?.to_owned(),
+            &i.discovery,
+            i.data(&pkg_name),
+            i.link_mode,
+        )?);
+    }
+
+    report.entry = db.entry(&install_folder).cloned();
+    Ok(report)
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem + Sync,
+    E: Executor,
+    P: Prompt,
+{
+    /// Wraps the executables of a directory on disk.
+    pub fn local(&self, i: OptLocal) -> err::Result<InstallReport> {
+        let path = self.fs.canonicalize(&i.path).map_err(|e| {
+            err::ErrorKind::PathNotFound(i.path.display().to_string(), e.to_string())
+        })?;
+        let i = OptLocal {
+            prefix: self.config.prefix(i.prefix),
+            bin_dir: self.config.bin_dir(i.bin_dir),
+            path,
+            ..i
+        };
+        let paths = local_paths(&i)?.with_cracker(&self.cracker);
+        let db = Mutex::new(preinstall(&self.fs, &paths)?);
+
+        let prompt = self.policy_prompt(&self.prompt);
+        let report = install_local(&self.fs, &prompt, &i, &paths, &db)?;
+        self.share_storage(&paths)?;
+        let db = db.into_inner().unwrap_or_else(|e| e.into_inner());
+        db.save(&self.fs, paths.db_path())?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+    use crate::executor::ScriptedExecutor;
+    use crate::{AssumeYes, CrackerDatabaseData, LinkMode};
+    use filesystem::{FakeFileSystem, FileSystem};
+    use std::path::PathBuf;
+
+    fn opt(copy: bool) -> OptLocal {
+        OptLocal {
+            prefix: Some(PathBuf::from("/prefix")),
+            bin_dir: None,
+            path: PathBuf::from("../src/tool/"),
+            name: None,
+            copy,
+            link_mode: LinkMode::Script,
+            discovery: Default::default(),
+            search_paths: vec![String::from("build")],
+            log: false,
+        }
+    }
+
+    fn setup() -> FakeFileSystem {
+        let fs = FakeFileSystem::new();
+        fs.create_dir_all("/src/tool/build").unwrap();
+        fs.create_dir_all("/work").unwrap();
+        fs.set_current_dir("/work").unwrap();
+        fs.create_file("/src/tool/build/tool", "#!/bin/sh").unwrap();
+        fs.set_mode(Path::new("/src/tool/build/tool"), 0o755)
+            .unwrap();
+        fs.create_file("/src/tool/build/tool.o", "").unwrap();
+        fs
+    }
+
+    #[test]
+    fn local_in_place() {
+        let cracker = Cracker::new(setup(), ScriptedExecutor::new(), AssumeYes);
+        let report = cracker.local(opt(false)).unwrap();
+
+        assert_eq!(report.wrappers_created, vec![String::from("tool")]);
+        let entry = report.entry.unwrap();
+        assert_eq!(
+            entry.data,
+            CrackerDatabaseData::Local {
+                pkg_name: String::from("tool"),
+                path: PathBuf::from("/src/tool"),
+                copied: false,
+                search_paths: vec![String::from("build")],
+                search_depth: 1,
+                exclude: vec![],
+            }
+        );
+        assert_eq!(
+            entry.wrappers[0].target,
+            Some(PathBuf::from("/src/tool/build/tool"))
+        );
+        assert_eq!(entry.install_folder, "/prefix/.cracker_storage/local_tool");
+        assert!(cracker.fs.is_dir("/prefix/.cracker_storage/local_tool"));
+        // the storage is shared, the directory on disk stays as it is.
+        assert_eq!(
+            cracker
+                .fs
+                .mode(Path::new("/src/tool/build/tool.o"))
+                .unwrap(),
+            0o644
+        );
+
+        // installing again removes the install folder only.
+        cracker.local(opt(false)).unwrap();
+        assert!(cracker.fs.is_file("/src/tool/build/tool"));
+    }
+
+    #[test]
+    fn local_copied() {
+        let cracker = Cracker::new(setup(), ScriptedExecutor::new(), AssumeYes);
+        let report = cracker.local(opt(true)).unwrap();
+
+        let copied = PathBuf::from("/prefix/.cracker_storage/local_tool/build/tool");
+        assert_eq!(
+            report.entry.unwrap().wrappers[0].target,
+            Some(copied.clone())
+        );
+        // still executable, and open to all users as everything in the storage.
+        assert_eq!(cracker.fs.mode(&copied).unwrap(), 0o777);
+        assert!(cracker
+            .fs
+            .is_file("/prefix/.cracker_storage/local_tool/build/tool.o"));
+
+        let e = cracker
+            .local(OptLocal {
+                path: PathBuf::from("/src/missing"),
+                ..opt(true)
+            })
+            .unwrap_err();
+        assert!(e.to_string().contains("unable to find /src/missing"));
+    }
+}
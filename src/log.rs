@@ -0,0 +1,213 @@
+use std::cell::RefCell;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use crate::executor::command_line;
+use crate::CrackerFileSystem;
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// colours of the old bash script: red, yellow and green.
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Level::Error => Some("\x1b[31m"),
+            Level::Warn => Some("\x1b[33m"),
+            Level::Info => Some("\x1b[32m"),
+            Level::Debug => None,
+        }
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// `-v` shows debug output, `-q` hides info and `-qq` warnings too, errors are always shown.
+pub fn set_verbosity(verbose: u8, quiet: u8) {
+    let level = (Level::Info as u8 + verbose)
+        .saturating_sub(quiet)
+        .min(Level::Debug as u8);
+    VERBOSITY.store(level, Ordering::Relaxed);
+}
+
+fn enabled(level: Level) -> bool {
+    level as u8 <= VERBOSITY.load(Ordering::Relaxed)
+}
+
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Sends all the log lines and questions to stderr, leaving stdout to the result of the command.
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
+}
+
+/// Shows a question to the user.
+pub fn prompt(text: &str) {
+    if stdout_reserved() {
+        eprintln!("{}", text);
+    } else {
+        println!("{}", text);
+    }
+}
+
+thread_local! {
+    /// Prepended to every log line of the current thread, lets parallel installs tell their output apart.
+    static LOG_PREFIX: RefCell<String> = const { RefCell::new(String::new()) };
+    /// Everything the thread logs while an [`InstallLog`] is alive, whatever the verbosity.
+    static CAPTURE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+pub fn set_log_prefix(prefix: &str) {
+    LOG_PREFIX.with(|p| *p.borrow_mut() = prefix.to_owned());
+}
+
+fn log_line(level: Level, text: &str) {
+    let line = LOG_PREFIX.with(|p| format!("{}{}", p.borrow(), text));
+    CAPTURE.with(|c| {
+        if let Some(captured) = c.borrow_mut().as_mut() {
+            captured.push_str(&line);
+            captured.push('\n');
+        }
+    });
+    if !enabled(level) {
+        return;
+    }
+
+    let colored = |tty: bool| match level.color() {
+        Some(color) if tty && std::env::var_os("NO_COLOR").is_none() => {
+            format!("{}{}\x1b[0m", color, line)
+        }
+        _ => line.clone(),
+    };
+    match level {
+        Level::Info | Level::Debug if !stdout_reserved() => {
+            println!("{}", colored(std::io::stdout().is_terminal()))
+        }
+        _ => eprintln!("{}", colored(std::io::stderr().is_terminal())),
+    }
+}
+
+pub fn debug(text: &str) {
+    log_line(Level::Debug, text);
+}
+
+pub fn info(text: &str) {
+    log_line(Level::Info, text);
+}
+
+pub fn warn(text: &str) {
+    log_line(Level::Warn, text);
+}
+
+pub fn error(text: &str) {
+    log_line(Level::Error, text);
+}
+
+/// Logs a command that was run along with everything it printed, shown with `-v` only.
+pub fn command(c: &Command, output: &Output) {
+    let mut text = format!("{} exited with {}", command_line(c), output.status);
+    for (name, stream) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        let stream = String::from_utf8_lossy(stream);
+        if !stream.trim().is_empty() {
+            text.push_str(&format!("\n{}:\n{}", name, stream.trim_end()));
+        }
+    }
+    debug(&text);
+}
+
+/// Keeps everything logged by the current thread, commands output included, and writes it to `path`
+/// once dropped, so a failed install can be looked into later.
+pub struct InstallLog<'a, Fs: CrackerFileSystem> {
+    fs: &'a Fs,
+    path: Option<PathBuf>,
+}
+
+impl<'a, Fs: CrackerFileSystem> InstallLog<'a, Fs> {
+    /// Does nothing unless `path` is given.
+    pub fn start(fs: &'a Fs, path: Option<PathBuf>) -> Self {
+        if path.is_some() {
+            CAPTURE.with(|c| *c.borrow_mut() = Some(String::new()));
+        }
+        Self { fs, path }
+    }
+}
+
+impl<Fs: CrackerFileSystem> Drop for InstallLog<'_, Fs> {
+    fn drop(&mut self) {
+        let captured = CAPTURE.with(|c| c.borrow_mut().take());
+        if let (Some(path), Some(captured)) = (&self.path, captured) {
+            let written = match path.parent() {
+                Some(dir) => self.fs.create_dir_all(dir),
+                None => Ok(()),
+            }
+            .and_then(|_| self.fs.write_file(path, captured));
+            match written {
+                Ok(()) => info(&format!("log written to {}", path.display())),
+                Err(e) => warn(&format!("Unable to write log to {}: {}", path.display(), e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod log_tests {
+    use super::*;
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    #[test]
+    fn install_log_keeps_everything() {
+        let fs = FakeFileSystem::new();
+        let path = PathBuf::from("/storage/.logs/conan_abc.log");
+        {
+            let _log = InstallLog::start(&fs, Some(path.clone()));
+            set_log_prefix("[abc] ");
+            info("installing");
+            let mut c = Command::new("conan");
+            c.arg("install");
+            command(
+                &c,
+                &crate::executor::output(1, "", "ERROR: recipe not found\n"),
+            );
+            set_log_prefix("");
+        }
+        info("not captured");
+
+        assert_eq!(
+            fs.read_file_to_string(&path).unwrap(),
+            "[abc] installing\n\
+             [abc] conan install exited with exit status: 1\n\
+             stderr:\n\
+             ERROR: recipe not found\n"
+        );
+
+        let _log = InstallLog::start(&fs, None);
+        info("nowhere");
+        assert!(CAPTURE.with(|c| c.borrow().is_none()));
+    }
+
+    #[test]
+    fn verbosity() {
+        set_verbosity(0, 1);
+        assert!(enabled(Level::Warn));
+        assert!(!enabled(Level::Info));
+        set_verbosity(0, 5);
+        assert!(enabled(Level::Error));
+        assert!(!enabled(Level::Warn));
+        set_verbosity(3, 0);
+        assert!(enabled(Level::Debug));
+        set_verbosity(0, 0);
+        assert!(enabled(Level::Info));
+        assert!(!enabled(Level::Debug));
+    }
+}
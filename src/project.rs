@@ -0,0 +1,286 @@
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use filesystem::FileSystem;
+
+use crate::{
+    err, wrapper_script, Cracker, CrackerDatabase, CrackerDatabaseEntry, CrackerFileSystem,
+    Executor, OptExec, Prompt, Wrapper,
+};
+
+/// Name of the file pinning tool versions for a directory tree.
+pub const PROJECT_FILE: &str = ".cracker";
+
+#[derive(Debug, PartialEq)]
+pub struct Pin {
+    pub name: String,
+    pub version: String,
+}
+
+/// A `.cracker` file, every line holds a package name and the version the project wants:
+///
+/// ```text
+/// # comments are fine
+/// clang-format 9.0.0
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct ProjectFile {
+    pub path: PathBuf,
+    pub pins: Vec<Pin>,
+}
+
+impl ProjectFile {
+    pub fn parse(path: PathBuf, content: &str) -> err::Result<Self> {
+        let mut pins = vec![];
+        for (no, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                [] => {}
+                [name, version] => pins.push(Pin {
+                    name: (*name).to_owned(),
+                    version: (*version).to_owned(),
+                }),
+                _ => {
+                    return Err(err::ErrorKind::ProjectFileInvalid(
+                        path,
+                        no + 1,
+                        line.trim().to_owned(),
+                    )
+                    .into())
+                }
+            }
+        }
+
+        Ok(Self { path, pins })
+    }
+
+    /// Looks for the project file in `dir` and all of its parents, the closest one wins.
+    pub fn find<Fs: FileSystem>(fs: &Fs, dir: &Path) -> err::Result<Option<Self>> {
+        for dir in dir.ancestors() {
+            let path = dir.join(PROJECT_FILE);
+            if fs.is_file(&path) {
+                let content = fs.read_file_to_string(&path)?;
+                return Ok(Some(Self::parse(path, &content)?));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn pin(&self, name: &str) -> Option<&Pin> {
+        self.pins.iter().find(|p| p.name == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Resolution<'a> {
+    pub entry: &'a CrackerDatabaseEntry,
+    pub wrapper: &'a Wrapper,
+    /// the project file that picked the version, `None` for the global default.
+    pub pinned_by: Option<PathBuf>,
+}
+
+/// Picks the package to run `binary` from: the version pinned by the project if there is one,
+/// the one owning the wrapper in the bin dir otherwise.
+pub fn resolve<'a>(
+    db: &'a CrackerDatabase,
+    project: Option<&ProjectFile>,
+    binary: &str,
+) -> err::Result<Resolution<'a>> {
+    let providers = db.providers(binary);
+    if providers.is_empty() {
+        return Err(err::ErrorKind::NotAWrapper(binary.to_owned()).into());
+    }
+
+    if let Some(project) = project {
+        let pin = providers
+            .iter()
+            .find_map(|(e, _)| project.pin(e.data.name()));
+        if let Some(pin) = pin {
+            let (entry, wrapper) = providers
+                .iter()
+                .find(|(e, _)| e.data.name() == pin.name && e.data.version() == Some(&pin.version))
+                .ok_or_else(|| {
+                    format!(
+                        "{} {} required by {} is not installed.",
+                        pin.name,
+                        pin.version,
+                        project.path.display()
+                    )
+                })?;
+            return Ok(Resolution {
+                entry,
+                wrapper,
+                pinned_by: Some(project.path.clone()),
+            });
+        }
+    }
+
+    let (entry, wrapper) = providers
+        .iter()
+        .find(|(_, w)| !w.shadowed)
+        .ok_or_else(|| format!("no default version of {} is set.", binary))?;
+    Ok(Resolution {
+        entry,
+        wrapper,
+        pinned_by: None,
+    })
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem,
+    E: Executor,
+    P: Prompt,
+{
+    /// Runs the version of the binary the project in the current directory pins, returns only on failure.
+    pub fn exec(&self, i: OptExec) -> err::Result<()> {
+        let fs = &self.fs;
+        let paths = self.storage(i.prefix, i.bin_dir);
+        let db = CrackerDatabase::read(fs, paths.db_path())?;
+        let project = ProjectFile::find(fs, &fs.current_dir()?)?;
+
+        let resolution = resolve(&db, project.as_ref(), &i.binary)?;
+        let binary = &i.binary;
+        let target = resolution.wrapper.target.as_ref().ok_or_else(|| {
+            format!(
+                "index does not know the binary of {}, reinstall {}.",
+                binary,
+                resolution.entry.data.describe()
+            )
+        })?;
+        let script = wrapper_script(
+            &resolution.entry.install_folder,
+            target,
+            resolution.entry.data.uses_conan_env(),
+        );
+
+        // only returns if the exec failed.
+        let e = Command::new("bash")
+            .arg("-c")
+            .arg(script)
+            .arg(&i.binary)
+            .args(&i.args)
+            .exec();
+        Err(e.into())
+    }
+}
+
+#[cfg(test)]
+mod project_tests {
+    use super::*;
+    use crate::conan_package::ConanPackage;
+    use crate::{CrackerDatabaseData, LinkMode};
+
+    fn entry(version: &str, shadowed: bool) -> CrackerDatabaseEntry {
+        CrackerDatabaseEntry {
+            data: CrackerDatabaseData::Conan {
+                conan_pkg: ConanPackage::new(&format!("clang-format/{}@", version)).unwrap(),
+                conan_settings: vec![],
+                conan_options: vec![],
+                recipe: None,
+                search_depth: 1,
+                exclude: vec![],
+            },
+            wrappers: vec![Wrapper {
+                wrapped_bin: String::from("clang-format"),
+                target: Some(PathBuf::from(format!("/{}/clang-format", version))),
+                shadowed,
+                link_mode: LinkMode::Script,
+            }],
+            install_folder: format!("/storage/conan_clang-format_{}", version),
+        }
+    }
+
+    #[test]
+    fn parse_project_file() {
+        let project = ProjectFile::parse(
+            PathBuf::from("/p/.cracker"),
+            "# tools\nclang-format 9.0.0\n\n  neovim   0.4.3 # editor\n",
+        )
+        .unwrap();
+        assert_eq!(
+            project.pins,
+            vec![
+                Pin {
+                    name: String::from("clang-format"),
+                    version: String::from("9.0.0"),
+                },
+                Pin {
+                    name: String::from("neovim"),
+                    version: String::from("0.4.3"),
+                },
+            ]
+        );
+
+        assert_eq!(
+            ProjectFile::parse(PathBuf::from("/p/.cracker"), "clang-format")
+                .err()
+                .unwrap()
+                .to_string(),
+            "/p/.cracker:1: expected '<package> <version>', got: 'clang-format'"
+        );
+    }
+
+    #[test]
+    fn find_closest_project_file() {
+        let fs = filesystem::FakeFileSystem::new();
+        fs.create_dir_all("/work/repo/src/deep").unwrap();
+        fs.create_file("/work/.cracker", "clang-format 10.0.0")
+            .unwrap();
+        fs.create_file("/work/repo/.cracker", "clang-format 9.0.0")
+            .unwrap();
+
+        let project = ProjectFile::find(&fs, Path::new("/work/repo/src/deep"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(project.path, PathBuf::from("/work/repo/.cracker"));
+        assert_eq!(project.pins[0].version, "9.0.0");
+
+        assert!(ProjectFile::find(&fs, Path::new("/elsewhere"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn resolve_pinned_and_default() {
+        let db = CrackerDatabase {
+            wrapped: vec![entry("9.0.0", true), entry("10.0.0", false)],
+            storage_owned_by: String::new(),
+        };
+
+        let default = resolve(&db, None, "clang-format").unwrap();
+        assert_eq!(default.entry.data.version(), Some("10.0.0"));
+        assert!(default.pinned_by.is_none());
+
+        let project =
+            ProjectFile::parse(PathBuf::from("/p/.cracker"), "clang-format 9.0.0").unwrap();
+        let pinned = resolve(&db, Some(&project), "clang-format").unwrap();
+        assert_eq!(pinned.entry.data.version(), Some("9.0.0"));
+        assert_eq!(pinned.pinned_by, Some(PathBuf::from("/p/.cracker")));
+
+        let unrelated = ProjectFile::parse(PathBuf::from("/p/.cracker"), "neovim 0.4.3").unwrap();
+        assert_eq!(
+            resolve(&db, Some(&unrelated), "clang-format")
+                .unwrap()
+                .entry
+                .data
+                .version(),
+            Some("10.0.0")
+        );
+
+        let missing =
+            ProjectFile::parse(PathBuf::from("/p/.cracker"), "clang-format 8.0.0").unwrap();
+        assert_eq!(
+            resolve(&db, Some(&missing), "clang-format")
+                .err()
+                .unwrap()
+                .to_string(),
+            "clang-format 8.0.0 required by /p/.cracker is not installed."
+        );
+
+        assert!(resolve(&db, None, "clang-tidy").is_err());
+    }
+}
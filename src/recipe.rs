@@ -0,0 +1,435 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use filesystem::DirEntry;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conan_paths, err, extract_git_repo_name, info, install_conan, is_local_repo, preinstall, warn,
+    ConanPackage, Cracker, CrackerDatabase, CrackerFileSystem, Executor, InstallReport, OptBuild,
+    OptInstall, OptRecipes, Paths, Prompt,
+};
+
+/// The recipes of `conanfiles/`, part of the binary so they can be built by name from anywhere.
+const BUNDLED: &[(&str, &str)] = &[
+    (
+        "clang-format",
+        include_str!("../conanfiles/clang-format/conanfile.py"),
+    ),
+    ("git", include_str!("../conanfiles/git/conanfile.py")),
+    ("llvm", include_str!("../conanfiles/llvm/conanfile.py")),
+    ("lz4", include_str!("../conanfiles/lz4/conanfile.py")),
+    ("neovim", include_str!("../conanfiles/neovim/conanfile.py")),
+    (
+        "subversion",
+        include_str!("../conanfiles/subversion/conanfile.py"),
+    ),
+    (
+        "texinfo",
+        include_str!("../conanfiles/texinfo/conanfile.py"),
+    ),
+    (
+        "utf8proc",
+        include_str!("../conanfiles/utf8proc/conanfile.py"),
+    ),
+];
+
+/// A recipe a package was created from, instead of being fetched from a remote.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Recipe {
+    /// directory holding the conanfile.py.
+    pub path: PathBuf,
+    /// profile of the machine doing the build.
+    pub profile: String,
+}
+
+/// A recipe `cracker recipes` lists.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct KnownRecipe {
+    pub name: String,
+    /// the configured source it comes from, 'bundled' for the recipes of the binary.
+    pub source: String,
+    /// not set for the bundled recipes, they are written out when used.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[non_exhaustive]
+pub struct RecipesReport {
+    pub recipes: Vec<KnownRecipe>,
+}
+
+impl std::fmt::Display for RecipesReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for recipe in self.recipes.iter() {
+            writeln!(f, "{:<16} {}", recipe.name, recipe.source)?;
+        }
+        Ok(())
+    }
+}
+
+fn bundled(name: &str) -> Option<&'static str> {
+    BUNDLED.iter().find(|(n, _)| *n == name).map(|(_, r)| *r)
+}
+
+/// Directory of the conanfile.py for `recipe`: a directory on disk, or else the bundled recipe named
+/// like its last component, written to the storage first.
+pub(crate) fn locate<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    paths: &Paths,
+    recipe: &Path,
+) -> err::Result<PathBuf> {
+    if fs.is_file(recipe.join("conanfile.py")) {
+        return Ok(fs.canonicalize(recipe)?);
+    }
+
+    let name = recipe
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match bundled(&name) {
+        Some(conanfile) => {
+            let dir = paths.recipes_dir().join(&name);
+            fs.create_dir_all(&dir)?;
+            fs.write_file(dir.join("conanfile.py"), conanfile)?;
+            Ok(dir)
+        }
+        None => {
+            let names: Vec<&str> = BUNDLED.iter().map(|(n, _)| *n).collect();
+            Err(err::ErrorKind::RecipeNotFound(
+                name,
+                format!(
+                    "no conanfile.py in {} and the bundled ones are: {}",
+                    recipe.display(),
+                    names.join(", ")
+                ),
+            )
+            .into())
+        }
+    }
+}
+
+/// A git repository unless it is a directory on disk other than a bare repository.
+fn is_git_source(source: &str) -> bool {
+    !is_local_repo(source) || source.ends_with(".git")
+}
+
+/// The directory holding the recipes of `source`, git sources are cloned into the storage once and
+/// pulled again on `update`.
+fn source_dir<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    paths: &Paths,
+    source: &str,
+    update: bool,
+) -> err::Result<PathBuf> {
+    if !is_git_source(source) {
+        return match fs.is_dir(source) {
+            true => Ok(PathBuf::from(source)),
+            false => Err(err::ErrorKind::NotADirectory(PathBuf::from(source)).into()),
+        };
+    }
+
+    let dir = paths
+        .recipe_sources_dir()
+        .join(extract_git_repo_name(source)?);
+    let mut c = Command::new("git");
+    if !fs.is_dir(&dir) {
+        info(&format!("Cloning recipes: {}", source));
+        c.args(["clone", source, "--depth", "1", err::path_str(&dir)?]);
+    } else if update {
+        info(&format!("Updating recipes: {}", source));
+        c.args(["pull", "--ff-only"]).current_dir(&dir);
+    } else {
+        return Ok(dir);
+    }
+    let output = executor.run(c)?;
+    if !output.status.success() {
+        return Err(err::ErrorKind::GitCloneFailure(source.to_owned(), output).into());
+    }
+
+    Ok(dir)
+}
+
+/// Recipes of the `sources`, then the bundled ones. The first recipe of a name hides the others.
+fn known_recipes<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    paths: &Paths,
+    sources: &[String],
+    update: bool,
+) -> Vec<KnownRecipe> {
+    let mut recipes: Vec<KnownRecipe> = vec![];
+    for source in sources {
+        let dir = match source_dir(fs, executor, paths, source, update) {
+            Ok(dir) => dir,
+            Err(e) => {
+                warn(&format!("Skipped recipe source {}: {}", source, e));
+                continue;
+            }
+        };
+        let mut found = vec![];
+        if let Ok(entries) = fs.read_dir(&dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if fs.is_file(entry.path().join("conanfile.py"))
+                    && !recipes.iter().any(|r| r.name == name)
+                {
+                    found.push(KnownRecipe {
+                        name,
+                        source: source.clone(),
+                        path: Some(entry.path()),
+                    });
+                }
+            }
+        }
+        recipes.extend(found);
+    }
+    for (name, _) in BUNDLED {
+        if !recipes.iter().any(|r| r.name == *name) {
+            recipes.push(KnownRecipe {
+                name: (*name).to_owned(),
+                source: String::from("bundled"),
+                path: None,
+            });
+        }
+    }
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    recipes
+}
+
+/// Directory of the recipe named `name`, see [`known_recipes`].
+fn find_recipe<Fs: CrackerFileSystem>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    paths: &Paths,
+    sources: &[String],
+    name: &str,
+) -> err::Result<Option<PathBuf>> {
+    let recipes = known_recipes(fs, executor, paths, sources, false);
+    match recipes.into_iter().find(|r| r.name == name) {
+        Some(KnownRecipe {
+            path: Some(path), ..
+        }) => Ok(Some(path)),
+        Some(_) => locate(fs, paths, Path::new(name)).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Installs the package, building it from a known recipe if conan is unable to, unless `i.no_build`.
+pub(crate) fn install_or_build<Fs, P>(
+    fs: &Fs,
+    executor: &dyn Executor,
+    prompt: &P,
+    i: &OptInstall,
+    sources: &[String],
+    paths: &Paths,
+    db: &Mutex<CrackerDatabase>,
+) -> err::Result<InstallReport>
+where
+    Fs: CrackerFileSystem,
+    P: Prompt + ?Sized,
+{
+    let failure = match install_conan(fs, executor, prompt, i, None, paths, db) {
+        Err(e) if !i.no_build && matches!(e.kind(), err::ErrorKind::ConanInstallFailure(_)) => e,
+        result => return result,
+    };
+
+    let conan_pkg = ConanPackage::new(&i.reference)?;
+    let path = match find_recipe(fs, executor, paths, sources, &conan_pkg.name)? {
+        Some(path) => path,
+        None => return Err(failure),
+    };
+    warn(&format!(
+        "Unable to install {}, building it from the recipe in {}.",
+        conan_pkg.full(),
+        path.display()
+    ));
+    // whatever the failed install left behind.
+    let install_folder = paths.install_folder();
+    if fs.is_dir(&install_folder) {
+        fs.remove_dir_all(&install_folder)?;
+    }
+    let recipe = Recipe {
+        path,
+        profile: String::from("default"),
+    };
+    install_conan(fs, executor, prompt, i, Some(&recipe), paths, db)
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem + Sync,
+    E: Executor,
+    P: Prompt,
+{
+    /// Creates a package from a recipe with conan, in cracker's storage, and wraps it as `install` does.
+    pub fn build(&self, i: OptBuild) -> err::Result<InstallReport> {
+        let prefix = self.config.prefix(i.prefix);
+        let bin_dir = self.config.bin_dir(i.bin_dir);
+        let storage = self.storage(prefix.clone(), bin_dir.clone());
+        let given = &i.recipe;
+        let path = if self.fs.is_file(Path::new(given).join("conanfile.py")) {
+            self.fs.canonicalize(Path::new(given))?
+        } else {
+            let sources = &self.config.recipes;
+            find_recipe(&self.fs, &self.executor, &storage, sources, given)?.ok_or_else(|| {
+                err::ErrorKind::RecipeNotFound(
+                    given.clone(),
+                    String::from("'cracker recipes' lists the known ones"),
+                )
+            })?
+        };
+        let name = match i.name {
+            Some(name) => name,
+            None => path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .ok_or_else(|| err::ErrorKind::UnableToName(path.clone()))?,
+        };
+        info(&format!("Recipe: {}", path.display()));
+        let recipe = Recipe {
+            path,
+            profile: i.profile,
+        };
+
+        let i = OptInstall {
+            prefix,
+            bin_dir,
+            reference: format!("{}/{}@", name, i.version),
+            discovery: i.discovery,
+            settings: self.config.settings(&name, i.settings),
+            options: self.config.options(&name, i.options),
+            no_build: true,
+            log: i.log,
+        };
+        let paths = conan_paths(&i)?.with_cracker(&self.cracker);
+        let db = Mutex::new(preinstall(&self.fs, &paths)?);
+
+        let prompt = self.policy_prompt(&self.prompt);
+        let report = install_conan(
+            &self.fs,
+            &self.executor,
+            &prompt,
+            &i,
+            Some(&recipe),
+            &paths,
+            &db,
+        )?;
+        self.share_storage(&paths)?;
+        let db = db.into_inner().unwrap_or_else(|e| e.into_inner());
+        db.save(&self.fs, paths.db_path())?;
+        Ok(report)
+    }
+
+    /// Lists the recipes `build` and `install` know by name.
+    pub fn recipes(&self, i: OptRecipes) -> err::Result<RecipesReport> {
+        let paths = self.storage(i.prefix, i.bin_dir);
+        let recipes = known_recipes(
+            &self.fs,
+            &self.executor,
+            &paths,
+            &self.config.recipes,
+            i.update,
+        );
+        Ok(RecipesReport { recipes })
+    }
+}
+
+#[cfg(test)]
+mod recipe_tests {
+    use super::*;
+    use crate::executor::{output, ScriptedExecutor};
+    use crate::InstallationType;
+    use filesystem::{FakeFileSystem, FileSystem};
+
+    #[test]
+    fn locate_recipes() {
+        let fs = FakeFileSystem::new();
+        let paths = Paths::new(
+            Some(PathBuf::from("/prefix")),
+            None,
+            InstallationType::Conan,
+            "lz4_1.9.2",
+        );
+        fs.create_dir_all("/work/recipes/lz4").unwrap();
+        fs.create_file("/work/recipes/lz4/conanfile.py", "mine")
+            .unwrap();
+        fs.set_current_dir("/work").unwrap();
+
+        assert_eq!(
+            locate(&fs, &paths, Path::new("recipes/lz4")).unwrap(),
+            PathBuf::from("/work/recipes/lz4")
+        );
+
+        let bundled = locate(&fs, &paths, Path::new("lz4")).unwrap();
+        assert_eq!(
+            bundled,
+            PathBuf::from("/prefix/.cracker_storage/.recipes/lz4")
+        );
+        let conanfile = fs
+            .read_file_to_string(bundled.join("conanfile.py"))
+            .unwrap();
+        assert!(conanfile.contains("name = \"lz4\""));
+        // an index from another machine still finds the bundled recipe.
+        assert_eq!(
+            locate(&fs, &paths, Path::new("/elsewhere/.recipes/lz4")).unwrap(),
+            bundled
+        );
+
+        let e = locate(&fs, &paths, Path::new("cmake")).unwrap_err();
+        assert!(e.to_string().contains("clang-format, git, llvm"));
+    }
+
+    #[test]
+    fn recipes_of_sources() {
+        let fs = FakeFileSystem::new();
+        let paths = Paths::storage(Some(PathBuf::from("/prefix")), None);
+        fs.create_dir_all("/srv/recipes/lz4").unwrap();
+        fs.create_file("/srv/recipes/lz4/conanfile.py", "").unwrap();
+        fs.create_dir_all("/srv/recipes/notes").unwrap();
+        let clone = "/prefix/.cracker_storage/.recipe_sources/team";
+        let executor = ScriptedExecutor::new().expect_with(
+            &format!("git clone git@host:team.git --depth 1 {}", clone),
+            {
+                let fs = fs.clone();
+                move |_| {
+                    fs.create_dir_all(format!("{}/tool", clone)).unwrap();
+                    fs.create_file(format!("{}/tool/conanfile.py", clone), "")
+                        .unwrap();
+                    Ok(output(0, "", ""))
+                }
+            },
+        );
+        let sources = vec![
+            String::from("/srv/recipes"),
+            String::from("git@host:team.git"),
+            String::from("/srv/missing"),
+        ];
+
+        let recipes = known_recipes(&fs, &executor, &paths, &sources, false);
+        executor.verify();
+        let listed: Vec<(&str, &str)> = recipes
+            .iter()
+            .map(|r| (r.name.as_str(), r.source.as_str()))
+            .collect();
+        assert!(listed.contains(&("lz4", "/srv/recipes")));
+        assert!(listed.contains(&("tool", "git@host:team.git")));
+        assert!(listed.contains(&("neovim", "bundled")));
+        assert!(!listed.iter().any(|(name, _)| *name == "notes"));
+        assert_eq!(listed.iter().filter(|(name, _)| *name == "lz4").count(), 1);
+
+        // cloned already, nothing is run.
+        assert_eq!(
+            find_recipe(&fs, &ScriptedExecutor::new(), &paths, &sources, "tool").unwrap(),
+            Some(PathBuf::from(clone).join("tool"))
+        );
+        assert_eq!(
+            find_recipe(&fs, &ScriptedExecutor::new(), &paths, &[], "cmake").unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+
+use crate::{err, CrackerDatabaseEntry, DoctorReport, Skipped};
+
+/// How a wrapper already present in the bin dir was dealt with.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Resolution {
+    /// the wrapper of another package was replaced.
+    Overwritten,
+    /// the wrapper of another package was left alone, nothing was created.
+    Kept,
+    /// another version of the same package provided it, the new one is the default now.
+    MadeDefault,
+    /// another version of the same package provided it and stays the default.
+    KeptDefault,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct Conflict {
+    pub wrapper: String,
+    /// the package owning the wrapper before the install.
+    pub provided_by: String,
+    pub resolution: Resolution,
+}
+
+/// What a single install did, printed as is by `--output json`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct InstallReport {
+    /// the index entry added, `None` when the install was given up.
+    pub(crate) entry: Option<CrackerDatabaseEntry>,
+    pub wrappers_created: Vec<String>,
+    pub wrappers_skipped: Vec<String>,
+    pub conflicts: Vec<Conflict>,
+    /// executables found in the search paths but not wrapped.
+    pub files_skipped: Vec<Skipped>,
+}
+
+impl InstallReport {
+    /// The package installed, as the index describes it.
+    pub fn package(&self) -> Option<String> {
+        self.entry.as_ref().map(|e| e.data.describe())
+    }
+
+    pub(crate) fn extend(&mut self, other: InstallReport) {
+        self.wrappers_created.extend(other.wrappers_created);
+        self.wrappers_skipped.extend(other.wrappers_skipped);
+        self.conflicts.extend(other.conflicts);
+        self.files_skipped.extend(other.files_skipped);
+    }
+}
+
+/// Output of a failed command as kept by [`err::ErrorKind::ConanInstallFailure`] and friends.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct CommandOutput {
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl From<&std::process::Output> for CommandOutput {
+    fn from(o: &std::process::Output) -> Self {
+        Self {
+            status: o.status.code(),
+            stdout: String::from_utf8_lossy(&o.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&o.stderr).into_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ErrorReport {
+    /// name of the [`err::ErrorKind`].
+    pub kind: &'static str,
+    pub message: String,
+    pub exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<CommandOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub import: Option<Box<ImportReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doctor: Option<Box<DoctorReport>>,
+}
+
+impl From<&err::Error> for ErrorReport {
+    fn from(e: &err::Error) -> Self {
+        let (output, import, doctor) = match e.kind() {
+            err::ErrorKind::ConanInstallFailure(o)
+            | err::ErrorKind::ConanCreateFailure(o)
+            | err::ErrorKind::GitCloneFailure(_, o)
+            | err::ErrorKind::GitBuildFailure(_, o)
+            | err::ErrorKind::ArchiveDownloadFailure(_, o)
+            | err::ErrorKind::ArchiveUnpackFailure(_, o)
+            | err::ErrorKind::ArchiveChecksumFailure(_, o) => (Some(o.into()), None, None),
+            err::ErrorKind::ImportFailure(report) => (None, Some(Box::new(report.clone())), None),
+            err::ErrorKind::DoctorIssues(report) => (None, None, Some(Box::new(report.clone()))),
+            _ => (None, None, None),
+        };
+        Self {
+            kind: e.kind().name(),
+            message: e
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(": "),
+            exit_code: e.kind().exit_code(),
+            output,
+            import,
+            doctor,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ImportFailure {
+    pub package: String,
+    pub error: ErrorReport,
+}
+
+/// What an import did, see [`crate::OptImport`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ImportReport {
+    pub installed: Vec<InstallReport>,
+    /// packages present in the storage already.
+    pub skipped: Vec<String>,
+    /// packages left out by `--only` and `--exclude`.
+    pub filtered_out: Vec<String>,
+    pub failed: Vec<ImportFailure>,
+}
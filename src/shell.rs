@@ -0,0 +1,124 @@
+use std::path::Path;
+
+/// Shells cracker can generate the enable snippet for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Tcsh,
+}
+
+impl Shell {
+    pub const ALL: [Shell; 4] = [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::Tcsh];
+
+    /// Guesses the shell from the `SHELL` environment variable.
+    pub fn detect() -> Option<Self> {
+        let shell = std::env::var("SHELL").ok()?;
+        Path::new(&shell).file_name()?.to_str()?.parse().ok()
+    }
+
+    /// Name of the enable script generated next to the bin dir, bash keeps the historical name.
+    pub fn enable_script_name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "cracker_enable",
+            Shell::Zsh => "cracker_enable.zsh",
+            Shell::Fish => "cracker_enable.fish",
+            Shell::Tcsh => "cracker_enable.tcsh",
+        }
+    }
+
+    /// Snippet putting `bin_dir` in front of PATH, sourcing it more than once does not add it again.
+    pub fn enable_script(&self, bin_dir: &Path) -> String {
+        let bin_dir = bin_dir.display();
+        match self {
+            Shell::Bash | Shell::Zsh => format!(
+                r#"
+case ":${{PATH}}:" in
+    *:"{bin_dir}":*) ;;
+    *) export PATH="{bin_dir}:${{PATH}}" ;;
+esac
+"#,
+                bin_dir = bin_dir
+            ),
+            Shell::Fish => format!(
+                r#"
+if not contains -- "{bin_dir}" $PATH
+    set -gx PATH "{bin_dir}" $PATH
+end
+"#,
+                bin_dir = bin_dir
+            ),
+            Shell::Tcsh => format!(
+                r#"
+if ( ":${{PATH}}:" !~ *:"{bin_dir}":* ) then
+    setenv PATH "{bin_dir}:${{PATH}}"
+endif
+"#,
+                bin_dir = bin_dir
+            ),
+        }
+        .trim()
+        .to_owned()
+    }
+}
+
+impl std::str::FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" | "sh" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "tcsh" | "csh" => Ok(Shell::Tcsh),
+            _ => Err(format!(
+                "unsupported shell: {}, only bash, zsh, fish and tcsh are.",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod shell_tests {
+    use super::*;
+
+    #[test]
+    fn parse_shell() {
+        assert_eq!("bash".parse(), Ok(Shell::Bash));
+        assert_eq!("csh".parse(), Ok(Shell::Tcsh));
+        assert!("powershell".parse::<Shell>().is_err());
+    }
+
+    #[test]
+    fn enable_scripts() {
+        let bin_dir = Path::new("/some/bin");
+        assert_eq!(
+            Shell::Fish.enable_script(bin_dir),
+            r#"if not contains -- "/some/bin" $PATH
+    set -gx PATH "/some/bin" $PATH
+end"#
+        );
+        assert_eq!(
+            Shell::Tcsh.enable_script(bin_dir),
+            r#"if ( ":${PATH}:" !~ *:"/some/bin":* ) then
+    setenv PATH "/some/bin:${PATH}"
+endif"#
+        );
+    }
+
+    #[test]
+    fn posix_enable_script_is_idempotent() {
+        let script = Shell::Bash.enable_script(Path::new("/some/bin"));
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("PATH=/usr/bin\n{0}\n{0}\necho \"$PATH\"", script))
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            "/some/bin:/usr/bin"
+        );
+    }
+}
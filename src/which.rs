@@ -0,0 +1,332 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::project::{resolve, ProjectFile};
+use crate::{
+    err, Cracker, CrackerDatabase, CrackerDatabaseData, CrackerFileSystem, Executor, LinkMode,
+    OptWhich, Paths, Prompt, WrapperScript,
+};
+
+/// Files generated by conan's virtualrunenv and virtualenv generators, sourced by the wrappers.
+const ENVIRONMENT_FILES: [&str; 2] = ["environment_run.sh.env", "environment.sh.env"];
+
+#[derive(Debug, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct WhichReport {
+    pub wrapper: PathBuf,
+    data: CrackerDatabaseData,
+    pub install_folder: String,
+    /// `None` when neither the index nor the wrapper on disk know it.
+    pub binary: Option<PathBuf>,
+    /// the wrapper picks the version from the project file.
+    pub shim: bool,
+    pub link_mode: LinkMode,
+    pub pinned_by: Option<PathBuf>,
+    /// other installed versions providing the same binary.
+    pub alternatives: Vec<String>,
+    pub environment: Vec<String>,
+}
+
+impl WhichReport {
+    /// The package providing the wrapper, as the index describes it.
+    pub fn package(&self) -> String {
+        self.data.describe()
+    }
+
+    pub(crate) fn new<Fs: CrackerFileSystem>(
+        fs: &Fs,
+        paths: &Paths,
+        db: &CrackerDatabase,
+        project: Option<&ProjectFile>,
+        binary: &str,
+    ) -> err::Result<Self> {
+        let resolution = resolve(db, project, binary)?;
+        let entry = resolution.entry;
+        let wrapper = paths.bin_dir().join(binary);
+        let script = WrapperScript::read(fs, &wrapper);
+        let shim = script.as_ref().map(|s| s.shim).unwrap_or(false);
+
+        let target = resolution.wrapper.target.clone().or_else(|| {
+            script
+                .as_ref()
+                .filter(|s| !s.shim)
+                .map(|s| s.target.clone())
+        });
+
+        let mut environment = vec![];
+        if entry.data.uses_conan_env() {
+            for file in ENVIRONMENT_FILES.iter() {
+                let file = Path::new(&entry.install_folder).join(file);
+                if let Ok(content) = fs.read_file_to_string(&file) {
+                    environment.extend(
+                        content
+                            .lines()
+                            .filter(|l| !l.trim().is_empty())
+                            .map(|l| l.to_owned()),
+                    );
+                }
+            }
+        }
+
+        // a hardlink across filesystems ended up a symlink.
+        let link_mode = match resolution.wrapper.link_mode {
+            LinkMode::Hardlink if fs.is_symlink(&wrapper) => LinkMode::Symlink,
+            link_mode => link_mode,
+        };
+
+        let alternatives = db
+            .providers(binary)
+            .into_iter()
+            .filter(|(e, _)| e.install_folder != entry.install_folder)
+            .map(|(e, _)| e.data.describe())
+            .collect();
+
+        Ok(Self {
+            wrapper,
+            data: entry.data.clone(),
+            install_folder: entry.install_folder.clone(),
+            binary: target,
+            shim,
+            link_mode,
+            pinned_by: resolution.pinned_by,
+            alternatives,
+            environment,
+        })
+    }
+}
+
+impl std::fmt::Display for WhichReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "wrapper: {}", self.wrapper.display())?;
+        match &self.data {
+            CrackerDatabaseData::Conan {
+                conan_pkg,
+                conan_settings,
+                conan_options,
+                recipe,
+                ..
+            } => {
+                writeln!(f, "conan package: {}", conan_pkg.full())?;
+                writeln!(f, "settings: [{}]", conan_settings.join(", "))?;
+                writeln!(f, "options: [{}]", conan_options.join(", "))?;
+                if let Some(recipe) = recipe {
+                    writeln!(
+                        f,
+                        "built from: {} with profile {}",
+                        recipe.path.display(),
+                        recipe.profile
+                    )?;
+                }
+            }
+            CrackerDatabaseData::Git {
+                url,
+                search_paths,
+                build_cmd,
+                recursive,
+                sparse,
+                ..
+            } => {
+                writeln!(f, "git repository: {}", url)?;
+                if *recursive {
+                    writeln!(f, "submodules: yes")?;
+                }
+                if !sparse.is_empty() {
+                    writeln!(f, "sparse checkout: [{}]", sparse.join(", "))?;
+                }
+                writeln!(f, "search paths: [{}]", search_paths.join(", "))?;
+                if let Some(build_cmd) = build_cmd {
+                    writeln!(f, "built with: {}", build_cmd)?;
+                }
+            }
+            CrackerDatabaseData::Archive {
+                source,
+                search_paths,
+                ..
+            } => {
+                writeln!(f, "archive: {}", source)?;
+                writeln!(f, "search paths: [{}]", search_paths.join(", "))?;
+            }
+            CrackerDatabaseData::Local {
+                path,
+                copied,
+                search_paths,
+                ..
+            } => {
+                match copied {
+                    true => writeln!(f, "copied from: {}", path.display())?,
+                    false => writeln!(f, "local directory: {}", path.display())?,
+                }
+                writeln!(f, "search paths: [{}]", search_paths.join(", "))?;
+            }
+        }
+        writeln!(f, "install folder: {}", self.install_folder)?;
+
+        match &self.binary {
+            Some(binary) => writeln!(f, "binary: {}", binary.display())?,
+            None => writeln!(f, "binary: unknown, the wrapper is missing or was modified")?,
+        }
+        if !self.shim && self.link_mode != LinkMode::Script {
+            writeln!(f, "wrapper is a {} to the binary", self.link_mode)?;
+        }
+        if self.shim {
+            match &self.pinned_by {
+                Some(project) => writeln!(f, "version pinned by: {}", project.display())?,
+                None => writeln!(f, "version: global default, no project file pins it")?,
+            }
+        }
+        for alternative in self.alternatives.iter() {
+            writeln!(f, "also installed: {}", alternative)?;
+        }
+
+        if !self.environment.is_empty() {
+            writeln!(f, "environment:")?;
+            for line in self.environment.iter() {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<Fs, E, P> Cracker<Fs, E, P>
+where
+    Fs: CrackerFileSystem,
+    E: Executor,
+    P: Prompt,
+{
+    /// Shows which package and version given wrapper runs.
+    pub fn which(&self, i: OptWhich) -> err::Result<WhichReport> {
+        let fs = &self.fs;
+        let paths = self.storage(i.prefix, i.bin_dir);
+        let db = CrackerDatabase::read(fs, paths.db_path())?;
+        let project = ProjectFile::find(fs, &fs.current_dir()?)?;
+
+        WhichReport::new(fs, &paths, &db, project.as_ref(), &i.binary)
+    }
+}
+
+#[cfg(test)]
+mod which_tests {
+    use super::*;
+    use crate::conan_package::ConanPackage;
+    use crate::executor::ScriptedExecutor;
+    use crate::{AssumeYes, Cracker, CrackerDatabaseEntry, OptWhich, Wrapper};
+    use filesystem::FileSystem;
+
+    #[test]
+    fn which_conan_wrapper() {
+        let fs = filesystem::FakeFileSystem::new();
+        let paths = Paths::storage(Some(PathBuf::from("/prefix")), None);
+        let if_path = "/prefix/.cracker_storage/conan_abc";
+        fs.create_dir_all("/prefix/bin").unwrap();
+        fs.create_dir_all(if_path).unwrap();
+        fs.create_file(
+            "/prefix/bin/abc",
+            format!(
+                "#!/bin/bash\nsource {p}/activate_run.sh\nsource {p}/activate.sh\n/pkg/bin/abc \"${{@}}\"",
+                p = if_path
+            ),
+        )
+        .unwrap();
+        fs.create_file(
+            format!("{}/environment_run.sh.env", if_path),
+            "PATH=\"/pkg/bin\"${PATH+:$PATH}\n\n",
+        )
+        .unwrap();
+
+        let data = CrackerDatabaseData::Conan {
+            conan_pkg: ConanPackage::new("abc/321@a/b").unwrap(),
+            conan_settings: vec![String::from("os=Linux")],
+            conan_options: vec![],
+            recipe: None,
+            search_depth: 1,
+            exclude: vec![],
+        };
+        let db = CrackerDatabase {
+            wrapped: vec![CrackerDatabaseEntry {
+                data: data.clone(),
+                wrappers: vec![Wrapper {
+                    wrapped_bin: String::from("abc"),
+                    target: None,
+                    shadowed: false,
+                    link_mode: LinkMode::Script,
+                }],
+                install_folder: String::from(if_path),
+            }],
+            storage_owned_by: whoami::username(),
+        };
+
+        let report = WhichReport::new(&fs, &paths, &db, None, "abc").unwrap();
+        assert_eq!(
+            report,
+            WhichReport {
+                wrapper: PathBuf::from("/prefix/bin/abc"),
+                data,
+                install_folder: String::from(if_path),
+                binary: Some(PathBuf::from("/pkg/bin/abc")),
+                shim: false,
+                link_mode: LinkMode::Script,
+                pinned_by: None,
+                alternatives: vec![],
+                environment: vec![String::from("PATH=\"/pkg/bin\"${PATH+:$PATH}")],
+            }
+        );
+        assert!(report
+            .to_string()
+            .contains("conan package: abc/321@a/b\nsettings: [os=Linux]\n"));
+
+        assert_eq!(
+            WhichReport::new(&fs, &paths, &db, None, "cba")
+                .err()
+                .unwrap()
+                .to_string(),
+            "cba is not a wrapper generated by cracker."
+        );
+    }
+
+    #[test]
+    fn which_in_storage_of_another_user() {
+        let fs = filesystem::FakeFileSystem::new();
+        fs.create_dir_all("/prefix/bin").unwrap();
+        fs.create_file(
+            "/prefix/bin/abc",
+            "#!/bin/bash
+/pkg/bin/abc \"${@}\"",
+        )
+        .unwrap();
+        let db = CrackerDatabase {
+            wrapped: vec![CrackerDatabaseEntry {
+                data: CrackerDatabaseData::Local {
+                    pkg_name: String::from("abc"),
+                    path: PathBuf::from("/pkg"),
+                    copied: false,
+                    search_paths: vec![],
+                    search_depth: 1,
+                    exclude: vec![],
+                },
+                wrappers: vec![Wrapper {
+                    wrapped_bin: String::from("abc"),
+                    target: Some(PathBuf::from("/pkg/bin/abc")),
+                    shadowed: false,
+                    link_mode: LinkMode::Script,
+                }],
+                install_folder: String::from("/pkg"),
+            }],
+            storage_owned_by: String::from("someone else"),
+        };
+        fs.create_file(
+            "/prefix/.cracker_index",
+            serde_json::to_string(&db).unwrap(),
+        )
+        .unwrap();
+
+        // only changing the storage is reserved to its owner.
+        let cracker = Cracker::new(fs, ScriptedExecutor::new(), AssumeYes);
+        let mut i = OptWhich::new("abc");
+        i.prefix = Some(PathBuf::from("/prefix"));
+        let report = cracker.which(i).unwrap();
+        assert_eq!(report.binary, Some(PathBuf::from("/pkg/bin/abc")));
+    }
+}
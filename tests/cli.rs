@@ -0,0 +1,974 @@
+mod common;
+
+use common::{serve, Sandbox, ORIGINAL_STORAGE_PATH};
+
+#[test]
+fn install_wraps_binary_and_restores_storage_path() {
+    let sandbox = Sandbox::new();
+    assert!(sandbox
+        .cracker(&["install", "abc/1.0@", "-s", "os=Linux"])
+        .status
+        .success());
+
+    let storage = sandbox.prefix().join(".cracker_storage");
+    let install_folder = storage.join("conan_abc_1.0");
+    assert_eq!(
+        sandbox.conan_calls(),
+        vec![
+            String::from("config get storage.path"),
+            format!(
+                "config set storage.path={}",
+                storage.join(".conan").display()
+            ),
+            format!(
+                "install abc/1.0@ -if {} -g virtualrunenv -g virtualenv -s os=Linux",
+                install_folder.display()
+            ),
+            format!("config set storage.path={}", ORIGINAL_STORAGE_PATH),
+        ]
+    );
+    assert_eq!(sandbox.storage_path(), ORIGINAL_STORAGE_PATH);
+    assert!(!storage.join(".conan_original_storage_path").exists());
+
+    assert_eq!(
+        sandbox.run_wrapper("abc", &["--version"], sandbox.root()),
+        "abc 1.0 --version"
+    );
+
+    let index = sandbox.index();
+    let entry = &index["wrapped"][0];
+    assert_eq!(entry["data"]["Conan"]["conan_pkg"]["name"], "abc");
+    assert_eq!(entry["data"]["Conan"]["conan_settings"][0], "os=Linux");
+    assert_eq!(entry["install_folder"], install_folder.to_str().unwrap());
+    assert_eq!(entry["wrappers"][0]["wrapped_bin"], "abc");
+
+    assert!(sandbox.bin_dir().join("abc").is_file());
+    assert!(sandbox.prefix().join("cracker_enable").is_file());
+}
+
+#[test]
+fn failed_install_leaves_no_trace() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.cracker(&["install", "broken/1.0@"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("recipe not found"));
+    assert_eq!(output.status.code(), Some(5));
+
+    assert_eq!(sandbox.storage_path(), ORIGINAL_STORAGE_PATH);
+    assert!(!sandbox.bin_dir().join("broken").exists());
+    assert!(!sandbox.prefix().join(".cracker_index").exists());
+}
+
+#[test]
+fn misspelled_wrappers_write_nothing() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.cracker(&["install", "abc/1.0@", "--wrappers", "abd"]);
+    assert_eq!(output.status.code(), Some(19));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("no executable matches wrapper(s): abd, available: abc"));
+    assert!(!sandbox.bin_dir().join("abc").exists());
+    assert!(!sandbox.prefix().join(".cracker_index").exists());
+
+    // nothing is left behind to be asked about.
+    let output = sandbox.cracker(&["install", "abc/1.0@", "--wrappers", "ab?"]);
+    assert!(output.status.success());
+    assert_eq!(sandbox.run_wrapper("abc", &[], sandbox.root()), "abc 1.0");
+}
+
+#[test]
+fn errors_of_every_subcommand_set_the_exit_code() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.cracker(&["git", "https://example.com"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unable to extract project name"));
+    assert_eq!(output.status.code(), Some(9));
+
+    let missing = sandbox.root().join("missing_index");
+    let output = sandbox.cracker(&["import", missing.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+
+    assert!(sandbox.cracker(&["install", "abc/1.0@"]).status.success());
+    let output = sandbox.cracker(&["which", "xyz"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("xyz is not a wrapper"));
+    assert_eq!(output.status.code(), Some(29));
+
+    let output = sandbox.cracker(&["local", "missing"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unable to find missing"));
+    assert_eq!(output.status.code(), Some(21));
+}
+
+#[test]
+fn quiet_install_keeps_the_log() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.cracker(&["install", "broken/1.0@", "--log", "-q"]);
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("recipe not found"));
+
+    let log = sandbox
+        .prefix()
+        .join(".cracker_storage/.logs/conan_broken_1.0.log");
+    let log = std::fs::read_to_string(log).unwrap();
+    assert!(log.contains("Installing package: broken/1.0@"), "{}", log);
+    assert!(log.contains("conan install broken/1.0@"), "{}", log);
+    assert!(
+        log.contains("ERROR: broken/1.0@: recipe not found"),
+        "{}",
+        log
+    );
+
+    let output = sandbox.cracker(&["install", "abc/1.0@", "-v"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("now invoking"));
+}
+
+#[test]
+fn versions_side_by_side_pinned_by_project() {
+    let sandbox = Sandbox::new();
+    assert!(sandbox.cracker(&["install", "abc/1.0@"]).status.success());
+    // nobody answers whether 2.0 should become the default - it does not.
+    assert!(sandbox.cracker(&["install", "abc/2.0@"]).status.success());
+
+    let project = sandbox.root().join("project");
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::write(project.join(".cracker"), "abc 2.0\n").unwrap();
+
+    assert_eq!(sandbox.run_wrapper("abc", &[], sandbox.root()), "abc 1.0");
+    assert_eq!(
+        sandbox.run_wrapper("abc", &["x", "y z"], &project.join("src")),
+        "abc 2.0 x y z"
+    );
+
+    std::fs::write(project.join(".cracker"), "abc 3.0\n").unwrap();
+    let output = std::process::Command::new(sandbox.bin_dir().join("abc"))
+        .current_dir(&project)
+        .env("PATH", sandbox.path_env())
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("abc 3.0 required by"));
+}
+
+#[test]
+fn import_into_another_prefix() {
+    let source = Sandbox::new();
+    assert!(source.cracker(&["install", "abc/1.0@"]).status.success());
+    assert!(source.cracker(&["install", "xyz/0.1@"]).status.success());
+
+    let target = Sandbox::new();
+    let index = source.prefix().join(".cracker_index");
+    let output = target.cracker(&["import", index.to_str().unwrap(), "--exclude", "xyz"]);
+    assert!(output.status.success());
+
+    assert_eq!(target.run_wrapper("abc", &[], target.root()), "abc 1.0");
+    assert!(!target.bin_dir().join("xyz").exists());
+    assert_eq!(target.storage_path(), ORIGINAL_STORAGE_PATH);
+
+    // everything is in place already, nothing gets installed again.
+    let calls = target.conan_calls().len();
+    assert!(target
+        .cracker(&["import", index.to_str().unwrap(), "--exclude", "xyz"])
+        .status
+        .success());
+    assert_eq!(target.conan_calls().len(), calls);
+}
+
+#[test]
+fn doctor_on_healthy_storage() {
+    let sandbox = Sandbox::new();
+    assert!(sandbox.cracker(&["install", "abc/1.0@"]).status.success());
+
+    let output = sandbox.cracker(&["doctor"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("no issues found."));
+}
+
+#[test]
+fn json_output() {
+    let sandbox = Sandbox::new();
+    let json = |output: &std::process::Output| -> serde_json::Value {
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let output = sandbox.cracker(&["install", "abc/1.0@", "--output", "json"]);
+    assert!(output.status.success());
+    let installed = json(&output);
+    assert_eq!(installed["command"], "install");
+    let result = &installed["result"];
+    assert_eq!(result["entry"]["data"]["Conan"]["conan_pkg"]["name"], "abc");
+    assert_eq!(result["wrappers_created"], serde_json::json!(["abc"]));
+    assert_eq!(result["conflicts"], serde_json::json!([]));
+
+    // nobody answers, the default stays.
+    let output = sandbox.cracker(&["install", "abc/2.0@", "--output", "json"]);
+    assert_eq!(
+        json(&output)["result"]["conflicts"],
+        serde_json::json!([{
+            "wrapper": "abc",
+            "provided_by": "abc/1.0@",
+            "resolution": "kept_default"
+        }])
+    );
+
+    let output = sandbox.cracker(&["install", "broken/1.0@", "--output", "json"]);
+    assert_eq!(output.status.code(), Some(5));
+    let error = &json(&output)["error"];
+    assert_eq!(error["kind"], "ConanInstallFailure");
+    assert_eq!(error["exit_code"], 5);
+    assert_eq!(error["output"]["status"], 1);
+    assert!(error["output"]["stderr"]
+        .as_str()
+        .unwrap()
+        .contains("recipe not found"));
+
+    let output = sandbox.cracker(&["which", "abc", "--output", "json"]);
+    assert_eq!(
+        json(&output)["result"]["data"]["Conan"]["conan_pkg"]["version"],
+        "1.0"
+    );
+}
+
+#[test]
+fn json_output_of_partial_import() {
+    let source = Sandbox::new();
+    assert!(source.cracker(&["install", "abc/1.0@"]).status.success());
+    let index = source.prefix().join(".cracker_index");
+    let mut content: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&index).unwrap()).unwrap();
+    let mut broken = content["wrapped"][0].clone();
+    broken["data"]["Conan"]["conan_pkg"]["name"] = "broken".into();
+    broken["wrappers"][0]["wrapped_bin"] = "broken".into();
+    content["wrapped"].as_array_mut().unwrap().push(broken);
+    std::fs::write(&index, content.to_string()).unwrap();
+
+    let target = Sandbox::new();
+    let output = target.cracker(&["import", index.to_str().unwrap(), "--output", "json"]);
+    assert_eq!(output.status.code(), Some(12));
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let import = &document["error"]["import"];
+    assert_eq!(
+        import["installed"][0]["wrappers_created"],
+        serde_json::json!(["abc"])
+    );
+    assert_eq!(import["failed"][0]["package"], "broken/1.0@");
+    assert_eq!(import["failed"][0]["error"]["kind"], "ConanInstallFailure");
+}
+
+#[test]
+fn json_output_of_doctor_and_gc() {
+    let sandbox = Sandbox::new();
+    let json = |output: &std::process::Output| -> serde_json::Value {
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    assert!(sandbox.cracker(&["install", "abc/1.0@"]).status.success());
+    let orphan = sandbox.prefix().join(".cracker_storage/git_gone");
+    std::fs::create_dir_all(&orphan).unwrap();
+
+    let output = sandbox.cracker(&["doctor", "--output", "json"]);
+    assert_eq!(output.status.code(), Some(20));
+    let orphaned = serde_json::json!({
+        "kind": "orphaned_install_folder",
+        "path": orphan,
+        "message": format!("install folder {} does not belong to any package", orphan.display()),
+        "fixed": false,
+    });
+    assert_eq!(
+        json(&output)["error"]["doctor"]["issues"],
+        serde_json::json!([orphaned])
+    );
+
+    let output = sandbox.cracker(&["doctor", "--fix", "--output", "json"]);
+    assert!(output.status.success());
+    assert_eq!(json(&output)["result"]["issues"][0]["fixed"], true);
+    assert!(!orphan.exists());
+
+    std::fs::create_dir_all(&orphan).unwrap();
+    std::fs::write(orphan.join("leftover"), "1234").unwrap();
+    let output = sandbox.cracker(&["gc", "--yes", "--output", "json"]);
+    assert!(output.status.success());
+    assert_eq!(
+        json(&output)["result"]["removed"],
+        serde_json::json!([{ "path": orphan, "size": 4 }])
+    );
+
+    let output = sandbox.cracker(&["doctor", "--output", "json"]);
+    assert_eq!(json(&output)["result"]["issues"], serde_json::json!([]));
+}
+
+#[test]
+fn config_env_and_command_line_layers() {
+    let sandbox = Sandbox::new();
+    std::fs::write(
+        sandbox.config_path(),
+        format!(
+            r#"
+prefix = "{}"
+conflicts = "overwrite"
+
+[packages.abc]
+settings = ["build_type=Debug"]
+"#,
+            sandbox.prefix().display()
+        ),
+    )
+    .unwrap();
+
+    // prefix and settings come from the config.
+    let output = sandbox.cracker_with_env(&["install", "abc/1.0@", "-s", "os=Linux"], &[]);
+    assert!(output.status.success());
+    assert!(sandbox.conan_calls().iter().any(
+        |c| c.starts_with("install abc/1.0@") && c.ends_with("-s build_type=Debug -s os=Linux")
+    ));
+    assert_eq!(sandbox.run_wrapper("abc", &[], sandbox.root()), "abc 1.0");
+
+    // nobody answers, the conflict policy makes the new version the default.
+    let output =
+        sandbox.cracker_with_env(&["install", "abc/2.0@", "-s", "build_type=Release"], &[]);
+    assert!(output.status.success());
+    assert!(sandbox
+        .conan_calls()
+        .iter()
+        .any(|c| c.starts_with("install abc/2.0@") && c.ends_with("-s build_type=Release")));
+    assert_eq!(sandbox.run_wrapper("abc", &[], sandbox.root()), "abc 2.0");
+
+    // the environment wins over the config, the command line over the environment.
+    let from_env = sandbox.root().join("from_env");
+    let output = sandbox.cracker_with_env(
+        &["install", "def/1.0@"],
+        &[("CRACKER_STORAGE_DIR", &from_env)],
+    );
+    assert!(output.status.success());
+    assert!(from_env.join("bin/def").is_file());
+
+    let from_cli = sandbox.root().join("from_cli");
+    let output = sandbox.cracker_with_env(
+        &[
+            "install",
+            "ghi/1.0@",
+            "--prefix",
+            from_cli.to_str().unwrap(),
+        ],
+        &[("CRACKER_STORAGE_DIR", &from_env)],
+    );
+    assert!(output.status.success());
+    assert!(from_cli.join("bin/ghi").is_file());
+    assert!(!from_env.join("bin/ghi").exists());
+
+    // without any of them the storage goes to the home directory.
+    std::fs::remove_file(sandbox.config_path()).unwrap();
+    let output = sandbox.cracker_with_env(&["install", "jkl/1.0@"], &[]);
+    assert!(output.status.success());
+    assert!(sandbox.root().join("home/.cracker_storage").is_dir());
+    assert!(sandbox.root().join("home/bin/jkl").is_file());
+
+    std::fs::write(sandbox.config_path(), "prefix = [").unwrap();
+    let output = sandbox.cracker_with_env(&["install", "jkl/1.0@"], &[]);
+    assert_eq!(output.status.code(), Some(13));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid config"));
+}
+
+/// Runs `program` in `dir`, returns what it printed.
+fn run_in(dir: &std::path::Path, program: &str, args: &[&str]) -> String {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{} {:?} failed", program, args);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn archives_from_a_url_and_a_path() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    let www = sandbox.root().join("www");
+    for (dir, name) in [("tool-1.0/bin", "tool"), ("other", "other")] {
+        let dir = www.join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(name),
+            format!("#!/bin/sh\necho \"{} from archive $*\"\n", name),
+        )
+        .unwrap();
+        std::fs::set_permissions(dir.join(name), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    run_in(&www, "tar", &["-czf", "tool-1.0.tar.gz", "tool-1.0"]);
+    run_in(&www, "zip", &["-qr", "other.zip", "other"]);
+    let sha256 = run_in(&www, "sha256sum", &["tool-1.0.tar.gz"]);
+    let sha256 = sha256.split_whitespace().next().unwrap();
+    let served = serve(www.clone());
+    let url = format!("{}/tool-1.0.tar.gz", served);
+
+    let output = sandbox.cracker(&["archive", &url, "--sha256", &"0".repeat(64)]);
+    assert_eq!(output.status.code(), Some(15));
+    let output = sandbox.cracker(&["archive", &format!("{}/missing.tar.gz", served)]);
+    assert_eq!(output.status.code(), Some(14));
+
+    let output = sandbox.cracker(&[
+        "archive",
+        &url,
+        "--sha256",
+        sha256,
+        "--strip-components",
+        "1",
+        "--search-paths",
+        "bin",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(
+        sandbox.run_wrapper("tool", &["x"], sandbox.root()),
+        "tool from archive x"
+    );
+
+    let output = sandbox.cracker(&["archive", "www/missing.zip"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unable to find www/missing.zip"));
+    assert_eq!(output.status.code(), Some(21));
+    // relative to the working directory, recorded as absolute for imports from elsewhere.
+    let output = sandbox.cracker(&["archive", "www/other.zip", "--search-paths", "other"]);
+    assert!(output.status.success());
+    assert_eq!(
+        sandbox.index()["wrapped"][1]["data"]["Archive"]["source"],
+        std::fs::canonicalize(www.join("other.zip"))
+            .unwrap()
+            .to_str()
+            .unwrap()
+    );
+    assert_eq!(
+        sandbox.run_wrapper("other", &[], sandbox.root()),
+        "other from archive"
+    );
+    assert!(sandbox
+        .prefix()
+        .join(".cracker_storage/archive_other/other/other")
+        .is_file());
+
+    let target = Sandbox::new();
+    let index = sandbox.prefix().join(".cracker_index");
+    let output = target.cracker(&["import", index.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        target.run_wrapper("tool", &[], target.root()),
+        "tool from archive"
+    );
+    assert_eq!(
+        target.run_wrapper("other", &[], target.root()),
+        "other from archive"
+    );
+}
+
+#[test]
+fn local_directories_in_place_and_copied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    let built = sandbox.root().join("built");
+    std::fs::create_dir_all(built.join("bin")).unwrap();
+    std::fs::write(built.join("bin/mine"), "#!/bin/sh\necho \"mine $*\"\n").unwrap();
+    std::fs::set_permissions(
+        built.join("bin/mine"),
+        std::fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+
+    let output = sandbox.cracker(&["local", "built", "--search-paths", "bin"]);
+    assert!(output.status.success());
+    assert_eq!(
+        sandbox.run_wrapper("mine", &["a"], sandbox.root()),
+        "mine a"
+    );
+    assert!(sandbox.cracker(&["doctor"]).status.success());
+
+    // a copy survives the directory it came from.
+    let copied = Sandbox::new();
+    let output = copied.cracker(&[
+        "local",
+        built.to_str().unwrap(),
+        "--name",
+        "mine_copy",
+        "--copy",
+        "--search-paths",
+        "bin",
+    ]);
+    assert!(output.status.success());
+    let index = copied.prefix().join(".cracker_index");
+    let imported = Sandbox::new();
+    assert!(imported
+        .cracker(&["import", index.to_str().unwrap()])
+        .status
+        .success());
+    std::fs::remove_dir_all(&built).unwrap();
+    assert_eq!(copied.run_wrapper("mine", &[], copied.root()), "mine");
+    assert_eq!(imported.run_wrapper("mine", &[], imported.root()), "mine");
+}
+
+#[test]
+fn wrappers_globs_exclude_and_search_depth() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    let built = sandbox.root().join("built");
+    std::fs::create_dir_all(built.join("bin/libexec")).unwrap();
+    for (name, content) in [
+        ("bin/clang-format", "#!/bin/sh\necho format\n"),
+        ("bin/clang-test", "#!/bin/sh\necho test\n"),
+        ("bin/libclang.so", "not an executable"),
+        ("bin/libexec/clang-helper", "#!/bin/sh\necho helper\n"),
+    ] {
+        std::fs::write(built.join(name), content).unwrap();
+        std::fs::set_permissions(built.join(name), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let output = sandbox.cracker(&[
+        "local",
+        "built",
+        "--search-paths",
+        "bin",
+        "--wrappers",
+        "*clang*",
+        "--exclude",
+        "*-test",
+        "--search-depth",
+        "2",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found 2 executable(s)"), "{}", stdout);
+    assert!(stdout.contains("clang-format, clang-helper, skipped 2."));
+    assert!(stdout.contains("bin/clang-test (excluded)."));
+    assert!(stdout.contains("bin/libclang.so (not runnable)."));
+    assert_eq!(
+        sandbox.run_wrapper("clang-helper", &[], sandbox.root()),
+        "helper"
+    );
+    assert!(!sandbox.bin_dir().join("clang-test").exists());
+    assert!(!sandbox.bin_dir().join("libclang.so").exists());
+
+    // the import looks for them the same way.
+    let data = &sandbox.index()["wrapped"][0]["data"]["Local"];
+    assert_eq!(data["search_depth"], 2);
+    assert_eq!(data["exclude"], serde_json::json!(["*-test"]));
+    let imported = Sandbox::new();
+    let index = sandbox.prefix().join(".cracker_index");
+    assert!(imported
+        .cracker(&["import", index.to_str().unwrap()])
+        .status
+        .success());
+    assert_eq!(
+        imported.run_wrapper("clang-helper", &[], imported.root()),
+        "helper"
+    );
+    assert!(!imported.bin_dir().join("clang-test").exists());
+}
+
+/// A bare repository `name` in `dir` with a Makefile building `bin/hello`.
+fn bare_repo(dir: &std::path::Path, name: &str) -> std::path::PathBuf {
+    let work = dir.join("work");
+    std::fs::create_dir_all(&work).unwrap();
+    std::fs::write(
+        work.join("Makefile"),
+        "all:\n\tmkdir -p bin\n\tprintf '#!/bin/sh\\necho \"hello $$*\"\\n' > bin/hello\n\tchmod +x bin/hello\n",
+    )
+    .unwrap();
+    run_in(&work, "git", &["init", "-q"]);
+    run_in(&work, "git", &["add", "Makefile"]);
+    run_in(
+        &work,
+        "git",
+        &[
+            "-c",
+            "user.name=a",
+            "-c",
+            "user.email=a@b",
+            "commit",
+            "-qm",
+            "make",
+        ],
+    );
+    run_in(dir, "git", &["clone", "-q", "--bare", "work", name]);
+    std::fs::remove_dir_all(&work).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn git_repositories_get_built() {
+    let sandbox = Sandbox::new();
+    let repo = bare_repo(sandbox.root(), "tools.git");
+    let url = format!("file://{}", repo.display());
+
+    let output = sandbox.cracker(&["git", &url, "--build-cmd", "false"]);
+    assert_eq!(output.status.code(), Some(17));
+
+    let output = sandbox.cracker(&["git", &url, "--build-cmd", "auto", "--search-paths", "bin"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("mkdir -p bin"));
+    assert_eq!(
+        sandbox.run_wrapper("hello", &["world"], sandbox.root()),
+        "hello world"
+    );
+
+    // the build command is part of the index, an import builds again.
+    let target = Sandbox::new();
+    let index = sandbox.prefix().join(".cracker_index");
+    let output = target.cracker(&["import", index.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(target.run_wrapper("hello", &[], target.root()), "hello");
+}
+
+#[test]
+fn symlink_and_hardlink_wrappers() {
+    use std::os::unix::fs::MetadataExt;
+
+    let sandbox = Sandbox::new();
+    let repo = bare_repo(sandbox.root(), "tools.git");
+    let url = format!("file://{}", repo.display());
+    let output = sandbox.cracker(&[
+        "git",
+        &url,
+        "--build-cmd",
+        "auto",
+        "--search-paths",
+        "bin",
+        "--link-mode",
+        "hardlink",
+    ]);
+    assert!(output.status.success());
+    let built = sandbox
+        .prefix()
+        .join(".cracker_storage/git_tools/bin/hello");
+    let wrapper = sandbox.bin_dir().join("hello");
+    assert_eq!(
+        std::fs::metadata(&wrapper).unwrap().ino(),
+        std::fs::metadata(&built).unwrap().ino()
+    );
+    assert_eq!(
+        sandbox.run_wrapper("hello", &["a"], sandbox.root()),
+        "hello a"
+    );
+
+    let mine = sandbox.root().join("mine");
+    std::fs::create_dir_all(&mine).unwrap();
+    std::fs::write(mine.join("mine"), "#!/bin/sh\necho \"$0\"\n").unwrap();
+    std::fs::set_permissions(
+        mine.join("mine"),
+        std::os::unix::fs::PermissionsExt::from_mode(0o755),
+    )
+    .unwrap();
+    let output = sandbox.cracker(&["local", "mine", "--link-mode", "symlink"]);
+    assert!(output.status.success());
+    let wrapper = sandbox.bin_dir().join("mine");
+    assert_eq!(std::fs::read_link(&wrapper).unwrap(), mine.join("mine"));
+    // the tool sees the path it was called by.
+    assert_eq!(
+        sandbox.run_wrapper("mine", &[], sandbox.root()),
+        wrapper.display().to_string()
+    );
+    let output = sandbox.cracker(&["which", "mine"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("wrapper is a symlink to the binary"));
+    assert!(sandbox.cracker(&["doctor"]).status.success());
+
+    // an import links the same way.
+    let imported = Sandbox::new();
+    let index = sandbox.prefix().join(".cracker_index");
+    assert!(imported
+        .cracker(&["import", index.to_str().unwrap()])
+        .status
+        .success());
+    assert!(imported.bin_dir().join("mine").is_symlink());
+    assert!(!imported.bin_dir().join("hello").is_symlink());
+
+    std::fs::remove_dir_all(&mine).unwrap();
+    assert!(!sandbox.cracker(&["doctor"]).status.success());
+    assert!(sandbox.cracker(&["doctor", "--fix"]).status.success());
+    assert!(std::fs::symlink_metadata(&wrapper).is_err());
+}
+
+#[test]
+fn git_urls_without_dot_git_and_local_paths() {
+    let sandbox = Sandbox::new();
+    std::fs::create_dir_all(sandbox.root().join("repos")).unwrap();
+    let repo = bare_repo(&sandbox.root().join("repos"), "tool");
+
+    let url = format!("file://{}/", repo.display());
+    let output = sandbox.cracker(&["git", &url, "--build-cmd", "make", "--search-paths", "bin"]);
+    assert!(output.status.success());
+    assert!(sandbox
+        .prefix()
+        .join(".cracker_storage/git_tool/bin/hello")
+        .is_file());
+    assert_eq!(sandbox.run_wrapper("hello", &[], sandbox.root()), "hello");
+
+    // a relative path is kept absolute so the index can be imported anywhere.
+    let named = Sandbox::new();
+    let relative = format!(
+        "../{}",
+        sandbox.root().file_name().unwrap().to_str().unwrap()
+    );
+    let output = named.cracker(&[
+        "git",
+        &format!("{}/repos/tool", relative),
+        "--name",
+        "greeter",
+        "--build-cmd",
+        "auto",
+        "--search-paths",
+        "bin",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(named.run_wrapper("hello", &["x"], named.root()), "hello x");
+    assert_eq!(
+        named.index()["wrapped"][0]["data"]["Git"]["url"],
+        repo.to_str().unwrap()
+    );
+
+    let target = Sandbox::new();
+    let index = named.prefix().join(".cracker_index");
+    assert!(target
+        .cracker(&["import", index.to_str().unwrap()])
+        .status
+        .success());
+    assert!(target
+        .prefix()
+        .join(".cracker_storage/git_greeter/bin/hello")
+        .is_file());
+
+    let output = sandbox.cracker(&["git", "missing/repo"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unable to find missing/repo"));
+}
+
+#[test]
+fn git_submodules_sparse_and_full_clones() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new();
+    let root = sandbox.root();
+    // submodules over file:// are refused by default.
+    let git_config = root.join("gitconfig");
+    std::fs::write(
+        &git_config,
+        "[protocol \"file\"]\n\tallow = always\n[user]\n\tname = a\n\temail = a@b\n",
+    )
+    .unwrap();
+    let git = |dir: &std::path::Path, args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", &git_config)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+    };
+
+    let vendor = root.join("vendor");
+    std::fs::create_dir_all(vendor.join("bin")).unwrap();
+    std::fs::write(vendor.join("bin/vendored"), "#!/bin/sh\necho vendored\n").unwrap();
+    std::fs::set_permissions(
+        vendor.join("bin/vendored"),
+        std::fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    git(&vendor, &["init", "-q"]);
+    git(&vendor, &["add", "."]);
+    git(&vendor, &["commit", "-qm", "vendored"]);
+    git(root, &["clone", "-q", "--bare", "vendor", "vendor.git"]);
+
+    let app = root.join("app");
+    std::fs::create_dir_all(app.join("docs")).unwrap();
+    std::fs::write(app.join("docs/readme"), "docs").unwrap();
+    git(&app, &["init", "-q"]);
+    git(&app, &["add", "."]);
+    git(&app, &["commit", "-qm", "docs"]);
+    let vendor_url = format!("file://{}", root.join("vendor.git").display());
+    git(
+        &app,
+        &["submodule", "-q", "add", &vendor_url, "tools/vendor"],
+    );
+    git(&app, &["commit", "-qm", "tools"]);
+    git(root, &["clone", "-q", "--bare", "app", "app.git"]);
+
+    let url = format!("file://{}", root.join("app.git").display());
+    let prefix = sandbox.prefix();
+    let output = sandbox.cracker_with_env(
+        &[
+            "git",
+            "--prefix",
+            prefix.to_str().unwrap(),
+            &url,
+            "--recursive",
+            "--depth",
+            "full",
+            "--sparse",
+            "tools",
+            "--search-paths",
+            "tools/vendor/bin",
+        ],
+        &[("GIT_CONFIG_GLOBAL", &git_config)],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        sandbox.run_wrapper("vendored", &[], sandbox.root()),
+        "vendored"
+    );
+    let clone = prefix.join(".cracker_storage/git_app");
+    assert!(!clone.join("docs").exists());
+    let commits = run_in(&clone, "git", &["rev-list", "--count", "HEAD"]);
+    assert_eq!(commits.trim(), "2");
+
+    let target = Sandbox::new();
+    let index = prefix.join(".cracker_index");
+    let target_prefix = target.prefix();
+    let output = target.cracker_with_env(
+        &[
+            "import",
+            "--prefix",
+            target_prefix.to_str().unwrap(),
+            index.to_str().unwrap(),
+        ],
+        &[("GIT_CONFIG_GLOBAL", &git_config)],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        target.run_wrapper("vendored", &[], target.root()),
+        "vendored"
+    );
+    assert!(!target_prefix.join(".cracker_storage/git_app/docs").exists());
+}
+
+#[test]
+fn build_bundled_and_local_recipes() {
+    let sandbox = Sandbox::new();
+    let output = sandbox.cracker(&["build", "lz4", "1.9.2"]);
+    assert!(output.status.success());
+    assert_eq!(sandbox.run_wrapper("lz4", &[], sandbox.root()), "lz4 1.9.2");
+    let bundled = sandbox.prefix().join(".cracker_storage/.recipes/lz4");
+    assert!(bundled.join("conanfile.py").is_file());
+    assert!(sandbox.conan_calls().contains(&format!(
+        "create {} lz4/1.9.2@ -pr:b default --build missing",
+        bundled.display()
+    )));
+    assert!(sandbox.cracker(&["doctor"]).status.success());
+
+    let recipe = sandbox.root().join("recipes/tool");
+    std::fs::create_dir_all(&recipe).unwrap();
+    std::fs::write(recipe.join("conanfile.py"), "class Tool: pass").unwrap();
+    let output = sandbox.cracker(&[
+        "build",
+        "recipes/tool",
+        "2.0",
+        "--profile",
+        "release",
+        "-s",
+        "os=Linux",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(sandbox.run_wrapper("tool", &[], sandbox.root()), "tool 2.0");
+    let index = sandbox.index();
+    let recipes: Vec<&serde_json::Value> = index["wrapped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| &e["data"]["Conan"]["recipe"])
+        .collect();
+    assert!(recipes.contains(&&serde_json::json!({
+        "path": recipe.to_str().unwrap(),
+        "profile": "release",
+    })));
+
+    // the recipe is part of the index, an import creates the package again.
+    let target = Sandbox::new();
+    let index = sandbox.prefix().join(".cracker_index");
+    assert!(target
+        .cracker(&["import", index.to_str().unwrap()])
+        .status
+        .success());
+    assert_eq!(target.run_wrapper("tool", &[], target.root()), "tool 2.0");
+    assert!(target.conan_calls().contains(&format!(
+        "create {} tool/2.0@ -pr:b release --build missing -s os=Linux",
+        recipe.display()
+    )));
+
+    std::fs::write(recipe.join("conanfile.py"), "broken").unwrap();
+    let output = sandbox.cracker(&["build", "recipes/tool", "2.1"]);
+    assert_eq!(output.status.code(), Some(18));
+    assert!(!sandbox
+        .prefix()
+        .join(".cracker_storage/conan_tool_2.1")
+        .exists());
+    let output = sandbox.cracker(&["build", "cmake", "3.18"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no recipe named cmake"));
+    assert_eq!(output.status.code(), Some(24));
+}
+
+#[test]
+fn install_falls_back_to_the_recipe_sources() {
+    let sandbox = Sandbox::new();
+    let recipes = sandbox.root().join("recipes");
+    std::fs::create_dir_all(recipes.join("neovim")).unwrap();
+    std::fs::write(recipes.join("neovim/conanfile.py"), "class Neovim: pass").unwrap();
+    std::fs::create_dir_all(recipes.join("docs")).unwrap();
+    let work = sandbox.root().join("work");
+    std::fs::create_dir_all(work.join("ripgrep")).unwrap();
+    std::fs::write(work.join("ripgrep/conanfile.py"), "class Rg: pass").unwrap();
+    run_in(&work, "git", &["init", "-q"]);
+    run_in(&work, "git", &["add", "."]);
+    run_in(
+        &work,
+        "git",
+        &[
+            "-c",
+            "user.name=a",
+            "-c",
+            "user.email=a@b",
+            "commit",
+            "-qm",
+            "rg",
+        ],
+    );
+    run_in(
+        sandbox.root(),
+        "git",
+        &["clone", "-q", "--bare", "work", "team.git"],
+    );
+    let team = sandbox.root().join("team.git");
+    std::fs::write(
+        sandbox.config_path(),
+        format!(
+            "recipes = [\"{}\", \"{}\"]\n",
+            recipes.display(),
+            team.display()
+        ),
+    )
+    .unwrap();
+
+    let output = sandbox.cracker(&["recipes"]);
+    assert!(output.status.success());
+    let listed = String::from_utf8_lossy(&output.stdout);
+    assert!(listed.contains(&format!("neovim           {}", recipes.display())));
+    assert!(listed.contains(&format!("ripgrep          {}", team.display())));
+    assert!(listed.contains("lz4              bundled"));
+    assert!(!listed.contains("docs"));
+
+    sandbox.without_binaries("neovim");
+    let output = sandbox.cracker(&["install", "neovim/0.4.3@", "--no-build"]);
+    assert_eq!(output.status.code(), Some(5));
+
+    let output = sandbox.cracker(&["install", "neovim/0.4.3@"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("building it from the recipe"));
+    assert_eq!(
+        sandbox.run_wrapper("neovim", &[], sandbox.root()),
+        "neovim 0.4.3"
+    );
+    assert!(sandbox.conan_calls().contains(&format!(
+        "create {} neovim/0.4.3@ -pr:b default --build missing",
+        recipes.join("neovim").display()
+    )));
+    assert_eq!(
+        sandbox.index()["wrapped"][0]["data"]["Conan"]["recipe"]["path"],
+        recipes.join("neovim").to_str().unwrap()
+    );
+
+    sandbox.without_binaries("ripgrep");
+    let output = sandbox.cracker(&["build", "ripgrep", "13.0"]);
+    assert!(output.status.success());
+    assert_eq!(
+        sandbox.run_wrapper("ripgrep", &[], sandbox.root()),
+        "ripgrep 13.0"
+    );
+}
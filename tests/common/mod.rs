@@ -0,0 +1,236 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use filesystem::{OsFileSystem, TempDir, TempFileSystem};
+
+/// Stands in for conan: keeps `storage.path` in a file next to itself, logs every call and on
+/// `install` fakes a package with a single binary named after it, printing its name, version and args.
+/// Packages named `broken` fail to install, those listed in `no_binaries` too until they are created.
+/// `create` only checks there is a conanfile.py, which fails if it mentions `broken`.
+const CONAN_STUB: &str = r#"#!/bin/bash
+state="$(dirname "$0")"
+echo "$*" >> "$state/conan_calls"
+case "$1 $2" in
+    "config get") cat "$state/storage_path"; exit 0 ;;
+    "config set") echo "${3#storage.path=}" > "$state/storage_path"; exit 0 ;;
+esac
+if [ "$1" = create ]; then
+    [ -f "$2/conanfile.py" ] || { echo "ERROR: no conanfile.py in $2" >&2; exit 1; }
+    if grep -q broken "$2/conanfile.py"; then
+        echo "ERROR: $3: error in build() method" >&2
+        exit 1
+    fi
+    echo "$3" >> "$state/created"
+    exit 0
+fi
+if [ "$1" != install ]; then
+    echo "unexpected conan call: $*" >&2
+    exit 2
+fi
+
+ref="$2"
+shift 2
+while [ $# -gt 0 ]; do
+    case "$1" in
+        -if) folder="$2"; shift 2 ;;
+        *) shift ;;
+    esac
+done
+name="${ref%%/*}"
+version="${ref#*/}"
+version="${version%%@*}"
+if [ "$name" = broken ]; then
+    echo "ERROR: $ref: recipe not found" >&2
+    exit 1
+fi
+if grep -qx "$name" "$state/no_binaries" 2>/dev/null && ! grep -qx "$ref" "$state/created" 2>/dev/null; then
+    echo "ERROR: Missing prebuilt package for '$ref'" >&2
+    exit 1
+fi
+
+bin="$(cat "$state/storage_path")/$name/$version/_/_/package/0/bin"
+mkdir -p "$bin" "$folder"
+printf '#!/bin/sh\necho "%s %s $*"\n' "$name" "$version" > "$bin/$name"
+chmod +x "$bin/$name"
+echo "PATH=\"$bin\"\${PATH+:\$PATH}" > "$folder/environment_run.sh.env"
+: > "$folder/activate_run.sh"
+: > "$folder/activate.sh"
+"#;
+
+pub const ORIGINAL_STORAGE_PATH: &str = "/home/someone/.conan/data";
+
+/// A temporary prefix and a directory with the conan stub, removed when dropped.
+pub struct Sandbox {
+    dir: filesystem::OsTempDir,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        let dir = OsFileSystem::new().temp_dir("cracker-it").unwrap();
+        let sandbox = Self { dir };
+        std::fs::create_dir_all(sandbox.stub_dir()).unwrap();
+        std::fs::create_dir_all(sandbox.prefix()).unwrap();
+
+        let conan = sandbox.stub_dir().join("conan");
+        std::fs::write(&conan, CONAN_STUB).unwrap();
+        std::fs::set_permissions(&conan, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(
+            sandbox.stub_dir().join("storage_path"),
+            ORIGINAL_STORAGE_PATH,
+        )
+        .unwrap();
+
+        sandbox
+    }
+
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn prefix(&self) -> PathBuf {
+        self.root().join("prefix")
+    }
+
+    pub fn bin_dir(&self) -> PathBuf {
+        self.prefix().join("bin")
+    }
+
+    fn stub_dir(&self) -> PathBuf {
+        self.root().join("stub")
+    }
+
+    /// What the stub holds as conan's `storage.path`.
+    pub fn storage_path(&self) -> String {
+        std::fs::read_to_string(self.stub_dir().join("storage_path"))
+            .unwrap()
+            .trim()
+            .to_owned()
+    }
+
+    /// Makes conan unable to install `name` unless it is created first.
+    pub fn without_binaries(&self, name: &str) {
+        let path = self.stub_dir().join("no_binaries");
+        let mut names = std::fs::read_to_string(&path).unwrap_or_default();
+        names.push_str(name);
+        names.push('\n');
+        std::fs::write(path, names).unwrap();
+    }
+
+    pub fn conan_calls(&self) -> Vec<String> {
+        std::fs::read_to_string(self.stub_dir().join("conan_calls"))
+            .unwrap_or_default()
+            .lines()
+            .map(|l| l.to_owned())
+            .collect()
+    }
+
+    pub fn index(&self) -> serde_json::Value {
+        let index = std::fs::read_to_string(self.prefix().join(".cracker_index")).unwrap();
+        serde_json::from_str(&index).unwrap()
+    }
+
+    /// The stubs, then cracker itself for the shims.
+    pub fn path_env(&self) -> String {
+        let cracker = Path::new(env!("CARGO_BIN_EXE_cracker"));
+        format!(
+            "{}:{}:{}",
+            self.stub_dir().display(),
+            cracker.parent().unwrap().display(),
+            std::env::var("PATH").unwrap_or_default()
+        )
+    }
+
+    /// The config cracker reads, missing unless a test writes it.
+    pub fn config_path(&self) -> PathBuf {
+        self.root().join("config.toml")
+    }
+
+    /// Runs cracker with `--prefix` pointing into the sandbox, nobody answers its questions.
+    pub fn cracker(&self, args: &[&str]) -> Output {
+        let (command, rest) = args.split_first().unwrap();
+        let prefix = self.prefix();
+        let mut full_args = vec![*command, "--prefix", prefix.to_str().unwrap()];
+        full_args.extend(rest);
+        self.cracker_with_env(&full_args, &[])
+    }
+
+    /// Runs cracker with the sandbox's config and the home directory in the sandbox, the prefix is
+    /// up to `args`, `envs` and the config.
+    pub fn cracker_with_env(&self, args: &[&str], envs: &[(&str, &Path)]) -> Output {
+        let output = Command::new(env!("CARGO_BIN_EXE_cracker"))
+            .args(args)
+            .env("PATH", self.path_env())
+            .env("HOME", self.root().join("home"))
+            .env("CRACKER_CONFIG", self.config_path())
+            .env_remove("CRACKER_STORAGE_DIR")
+            .env_remove("CRACKER_STORAGE_BIN")
+            .envs(envs.iter().copied())
+            .current_dir(self.root())
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        println!(
+            "cracker {:?}:\n{}",
+            args,
+            String::from_utf8_lossy(&output.stdout)
+        );
+        eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+        output
+    }
+
+    /// Runs a wrapper from the bin dir in `dir`, returns what it printed.
+    pub fn run_wrapper(&self, name: &str, args: &[&str], dir: &Path) -> String {
+        let output = Command::new(self.bin_dir().join(name))
+            .args(args)
+            .env("PATH", self.path_env())
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_owned()
+    }
+}
+
+/// Serves the files of `dir` over http until the tests are done, returns the URL of `dir`.
+pub fn serve(dir: PathBuf) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut request = String::new();
+            let _ = reader.read_line(&mut request);
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|n| n > 2) {
+                header.clear();
+            }
+
+            let file = request.split_whitespace().nth(1).unwrap_or("/");
+            let response = match std::fs::read(dir.join(file.trim_start_matches('/'))) {
+                Ok(body) => {
+                    let mut response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend(body);
+                    response
+                }
+                Err(_) => {
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_vec()
+                }
+            };
+            let _ = stream.write_all(&response);
+        }
+    });
+    url
+}